@@ -1,7 +1,7 @@
 #![allow(dead_code, unused_variables)]
 
 use std::sync::Arc;
-use async_tasks_state_map::{AsyncTasksRecorder, TaskState};
+use async_tasks_state_map::{AsyncTasksRecorder, RecordedState};
 
 struct SimulatedStream {}
 
@@ -11,13 +11,48 @@ struct UploadFileArgs {
     md5: String,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// Recorded by the recorder directly.
+#[derive(Debug, Clone, Eq, PartialEq)]
 enum UploadTaskState {
     Uploading,
+    /// Only with a concurrency limit.
+    Queued,
     Success,
     Failed,
     NotFound,
     Revoking,
+    /// Only with `RevokeFailurePolicy::RevokeFailed`.
+    RevokeFailed,
+}
+
+impl RecordedState for UploadTaskState {
+    fn working() -> Self {
+        UploadTaskState::Uploading
+    }
+
+    fn queued() -> Self {
+        UploadTaskState::Queued
+    }
+
+    fn success() -> Self {
+        UploadTaskState::Success
+    }
+
+    fn failed() -> Self {
+        UploadTaskState::Failed
+    }
+
+    fn not_found() -> Self {
+        UploadTaskState::NotFound
+    }
+
+    fn revoking() -> Self {
+        UploadTaskState::Revoking
+    }
+
+    fn revoke_failed() -> Self {
+        UploadTaskState::RevokeFailed
+    }
 }
 
 fn main() {
@@ -31,7 +66,7 @@ fn main() {
 /// Simulate front-end request.
 async fn simulate_requests() {
     println!("hello world!");
-    let recorder = AsyncTasksRecorder::new();
+    let recorder = AsyncTasksRecorder::default();
     let fake_md5 = "d8q793wye1u3".to_string();
 
     println!("REQUEST: check_upload_state {}", fake_md5);
@@ -90,7 +125,7 @@ async fn simulate_requests() {
 // APIs -----------
 
 /// launch
-async fn upload_file(recorder: AsyncTasksRecorder<Arc<String>>, args: UploadFileArgs) {
+async fn upload_file(recorder: AsyncTasksRecorder<Arc<String>, UploadTaskState>, args: UploadFileArgs) {
     let destination = "some place".to_string(); // decided by some algorithm
     let fut = async move {
         println!("upload_to_destination start!");
@@ -112,21 +147,13 @@ async fn upload_file(recorder: AsyncTasksRecorder<Arc<String>>, args: UploadFile
 }
 
 /// check
-async fn check_upload_state(recorder: AsyncTasksRecorder<Arc<String>>, arg_md5: String) -> UploadTaskState {
+async fn check_upload_state(recorder: AsyncTasksRecorder<Arc<String>, UploadTaskState>, arg_md5: String) -> UploadTaskState {
     let arg_md5 = Arc::new(arg_md5);
-    let res = recorder.query_task_state(&arg_md5).await;
-
-    match res {
-        TaskState::Success => UploadTaskState::Success,
-        TaskState::Failed => UploadTaskState::Failed,
-        TaskState::NotFound => UploadTaskState::NotFound,
-        TaskState::Working => UploadTaskState::Uploading,
-        TaskState::Revoking => UploadTaskState::Revoking,
-    }
+    recorder.query_task_state(&arg_md5).await
 }
 
 /// revoke
-async fn delete_file(recorder: AsyncTasksRecorder<Arc<String>>, arg_md5: String) -> bool {
+async fn delete_file(recorder: AsyncTasksRecorder<Arc<String>, UploadTaskState>, arg_md5: String) -> bool {
     let arg_md5 = Arc::new(arg_md5);
     let fut = async move {
         println!("delete_file start!");
@@ -4,11 +4,17 @@
 //!
 //! Just look at the [`AsyncTasksRecorder`](AsyncTasksRecorder).
 //!
+//! Use [`AsyncTasksResultRecorder`](AsyncTasksResultRecorder) if the outputs of tasks are needed.
+//!
 
 mod models;
 mod recorder;
+mod result_recorder;
+mod spawner;
 
 pub use models::*;
 pub use recorder::*;
+pub use result_recorder::*;
+pub use spawner::*;
 
 pub use scc;
@@ -1,10 +1,215 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
+
 #[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TaskState {
     /// Running or pending.
     Working,
+    /// Waiting for a slot when the concurrency limit is reached.
+    Queued,
     Success,
     Failed,
     /// Never appear in map, only returned by query when the target task is not in map.
     NotFound,
     Revoking,
+    /// The revoking failed, only with [`RevokeFailurePolicy::RevokeFailed`](crate::RevokeFailurePolicy::RevokeFailed).
+    RevokeFailed,
+}
+
+/// The type of the states recorded by an [`AsyncTasksRecorder`](crate::AsyncTasksRecorder), which is [`TaskState`] by default.
+///
+/// Besides its own settled states, which are only entered by the rules of a [`TransitionTable`](crate::TransitionTable),
+/// the type should express the states entered by the recorder itself, and they should be different from each other.
+/// See [`TaskState`] for what each of them means.
+pub trait RecordedState: Eq + Clone + std::fmt::Debug + Send + Sync + 'static {
+    /// See [`TaskState::Working`].
+    fn working() -> Self;
+    /// See [`TaskState::Queued`].
+    fn queued() -> Self;
+    /// See [`TaskState::Success`].
+    fn success() -> Self;
+    /// See [`TaskState::Failed`].
+    fn failed() -> Self;
+    /// See [`TaskState::NotFound`].
+    fn not_found() -> Self;
+    /// See [`TaskState::Revoking`].
+    fn revoking() -> Self;
+    /// See [`TaskState::RevokeFailed`].
+    fn revoke_failed() -> Self;
+}
+
+impl RecordedState for TaskState {
+    fn working() -> Self {
+        TaskState::Working
+    }
+
+    fn queued() -> Self {
+        TaskState::Queued
+    }
+
+    fn success() -> Self {
+        TaskState::Success
+    }
+
+    fn failed() -> Self {
+        TaskState::Failed
+    }
+
+    fn not_found() -> Self {
+        TaskState::NotFound
+    }
+
+    fn revoking() -> Self {
+        TaskState::Revoking
+    }
+
+    fn revoke_failed() -> Self {
+        TaskState::RevokeFailed
+    }
+}
+
+/// The reason why a task is `Failed`.
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FailureReason {
+    /// The task returned `Err`.
+    Error,
+    /// The task panicked.
+    Panicked,
+    /// The task was aborted by `abort_task`.
+    Aborted,
+    /// The task did not finish before its deadline.
+    TimedOut,
+    /// The `Future` executing the task was dropped before the task finished,
+    /// e.g. the `tokio` task was aborted or the caller of `launch_block` stopped awaiting.
+    Dropped,
+    /// A prerequisite of the task failed or was removed before the task started.
+    DependencyFailed,
+}
+
+/// The payload of a panic, see [`std::panic::catch_unwind`].
+pub type PanicPayload = Box<dyn Any + Send + 'static>;
+
+/// The reason given by a [`TransitionGuard`](crate::TransitionGuard) to reject a launch or a revoking.
+///
+/// Wrap any error type, which can be got back by [`downcast_ref`](Self::downcast_ref).
+#[derive(Debug, Clone)]
+pub struct Rejection(Arc<dyn Error + Send + Sync + 'static>);
+
+impl Rejection {
+    /// Create by the reason.
+    pub fn new<E>(reason: E) -> Self
+        where E: Error + Send + Sync + 'static {
+        Rejection(Arc::new(reason))
+    }
+
+    /// Get the reason if it is of type `E`.
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+        where E: Error + 'static {
+        self.0.downcast_ref()
+    }
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for Rejection {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
+
+/// The error of a `Future` for revoking executed asynchronously,
+/// see [`AsyncTasksRecorder::query_revoke_error`](crate::AsyncTasksRecorder::query_revoke_error).
+///
+/// Wrap any error type, which can be got back by [`with_downcast`](Self::with_downcast).
+/// The error is behind a lock, so it is not required to be `Sync`.
+#[derive(Clone)]
+pub struct RevokeError(Arc<Mutex<dyn Any + Send + 'static>>);
+
+impl RevokeError {
+    /// Create by the error.
+    pub fn new<E>(error: E) -> Self
+        where E: Send + 'static {
+        RevokeError(Arc::new(Mutex::new(error)))
+    }
+
+    /// Whether the error is of type `E`.
+    pub fn is<E>(&self) -> bool
+        where E: 'static {
+        self.lock().is::<E>()
+    }
+
+    /// Call `f` with the error if it is of type `E`, and return its output.
+    pub fn with_downcast<E, T, F>(&self, f: F) -> Option<T>
+        where E: 'static,
+              F: FnOnce(&E) -> T {
+        self.lock().downcast_ref().map(f)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, dyn Any + Send + 'static> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for RevokeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("RevokeError").finish_non_exhaustive()
+    }
+}
+
+/// The quota of a group which is exceeded. See [`GroupLimits`](crate::GroupLimits).
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Quota {
+    /// The number of the `Working` tasks in the group has reached `max_working`.
+    Working,
+    /// The number of the tasks recorded in the group has reached `max_entries`.
+    Entries,
+}
+
+/// The reason why a launch or a revoking is refused. The state of the task is left unchanged.
+#[derive(Debug, Clone)]
+pub enum Refusal<S = TaskState> {
+    /// The task is not in a state allowing it. The current state is included.
+    State(S),
+    /// Rejected by a [`TransitionGuard`](crate::TransitionGuard).
+    Rejected(Rejection),
+    /// The quota of the task's group is exceeded.
+    QuotaExceeded(Quota),
+    /// The task would depend on itself.
+    DependencyCycle,
+    /// The task has no [`Compensation`](crate::Compensation) to revoke it.
+    NoCompensation,
+}
+
+impl<S> Refusal<S> {
+    /// Get the current state if refused because of the state.
+    pub fn state(&self) -> Option<&S> {
+        match self {
+            Refusal::State(state) => Some(state),
+            _ => None,
+        }
+    }
+
+    /// Get the reason if rejected by a guard.
+    pub fn rejection(&self) -> Option<&Rejection> {
+        match self {
+            Refusal::Rejected(rejection) => Some(rejection),
+            _ => None,
+        }
+    }
+
+    /// Get the exceeded quota if refused because of the group limits.
+    pub fn quota(&self) -> Option<Quota> {
+        match self {
+            Refusal::QuotaExceeded(quota) => Some(*quota),
+            _ => None,
+        }
+    }
 }
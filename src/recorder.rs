@@ -1,37 +1,178 @@
 use std::borrow::Borrow;
-use std::future::Future;
+use std::future::{poll_fn, Future};
 use std::hash::Hash;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::{pin, Pin};
 use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use scc::hash_map::Entry;
+use tokio::sync::{broadcast, watch, Notify};
 use crate::*;
 
+mod abort;
+mod compensation;
+mod dependency;
+mod events;
+mod expiry;
+mod group;
+mod guard;
+mod journal;
+mod limit;
+mod observer;
+mod options;
+mod panic;
+mod progress;
+mod retry;
+mod revoke_failure;
+mod revoke_working;
+mod runtime;
+mod snapshot;
+mod task_handle;
+mod timeout;
+mod transition;
+mod wait;
+
+pub(crate) use abort::CancelReceiver;
+use abort::Canceller;
+use dependency::DependencyGraph;
+use events::DEFAULT_EVENT_CAPACITY;
+use expiry::{ExpireCleanup, ExpireHook, TtlConfig};
+use journal::Journal;
+use group::Grouping;
+use limit::Admission;
+pub(crate) use limit::{LaunchTicket, Slot};
+use panic::{PanicHook, DropGuard};
+use revoke_failure::wrap_revoke_error;
+use revoke_working::PendingRevoke;
+use transition::is_in_progress;
+
+pub use compensation::{Compensation, CompensationFuture};
+pub use expiry::SweeperHandle;
+pub use events::{EventsLagged, TransitionCause, TransitionEvent, TransitionFilter, TransitionStream};
+pub use guard::{GuardFuture, TransitionGuard};
+pub use observer::TransitionObserver;
+pub use options::LaunchOptions;
+pub use progress::{Progress, ProgressPayload, ProgressReporter};
+pub use group::{GroupLimits, WhenBusy};
+pub use retry::{Backoff, RetryPolicy};
+pub use revoke_failure::RevokeFailurePolicy;
+pub use revoke_working::WhenWorking;
+pub use snapshot::RestoreOptions;
+pub use task_handle::{TaskError, TaskHandle};
+pub use transition::{DefaultTransitions, TransitionTable};
+
 /// Thread-safe. Can be shared by `cloning` (`Arc` is used internally).
-#[derive(Debug, Clone)]
-pub struct AsyncTasksRecorder<K>
-    where K: Eq + Hash + Clone + Send + Sync + 'static {
-    recorder: Arc<scc::HashMap<K, TaskState>>,
+///
+/// The states of the tasks are [`TaskState`] by default, and can be replaced by another [`RecordedState`].
+#[derive(Clone)]
+pub struct AsyncTasksRecorder<K, S = TaskState>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    recorder: Arc<scc::HashMap<K, S>>,
+    /// Notifiers of the tasks being waited for.
+    notifiers: Arc<scc::HashMap<K, Arc<Notify>>>,
+    /// Cancellers of the `Working` tasks.
+    cancellers: Arc<scc::HashMap<K, Canceller>>,
+    /// Reasons of the `Failed` tasks.
+    failures: Arc<scc::HashMap<K, FailureReason>>,
+    /// Numbers of attempts of the tasks launched with retry.
+    attempts: Arc<scc::HashMap<K, usize>>,
+    /// Called when a task executed asynchronously panics.
+    panic_hook: Option<PanicHook<K>>,
+    /// Time-to-live of the finished tasks.
+    ttl: TtlConfig,
+    /// When the finished tasks finished. Only recorded when `ttl` is enabled.
+    finished_at: Arc<scc::HashMap<K, Instant>>,
+    /// Called when an expired task is removed.
+    expire_hook: Option<ExpireHook<K, S>>,
+    /// Called while an expired task is being removed, set by the wrappers of the recorder.
+    expire_cleanup: Option<ExpireCleanup<K>>,
+    /// Records every change of state to a file.
+    journal: Option<Arc<Journal<K, S>>>,
+    /// Executes the tasks in the background. The default one is used if it is `None`.
+    spawner: Option<Arc<dyn Spawner>>,
+    /// Sends the events of state transitions to the subscribers.
+    events: broadcast::Sender<TransitionEvent<K, S>>,
+    /// Called inline when a task changes its state.
+    observers: Arc<Vec<Arc<dyn TransitionObserver<K, S>>>>,
+    /// Decide whether a task can be launched or revoked.
+    guards: Arc<Vec<Arc<dyn TransitionGuard<K, S>>>>,
+    /// Locks of the tasks whose guards are being checked.
+    guard_locks: Arc<scc::HashMap<K, Arc<tokio::sync::Mutex<()>>>>,
+    /// Limits the number of the `Working` tasks.
+    admission: Option<Arc<Admission>>,
+    /// Passed to `admission` whenever it is built.
+    priority_aging: Option<Duration>,
+    /// Maps the tasks to their groups, which are limited by `admission`.
+    grouping: Option<Arc<dyn Grouping<K>>>,
+    /// The prerequisites of the tasks waiting for them.
+    dependencies: DependencyGraph<K>,
+    /// The progress reported by the `Working` tasks.
+    progress: Arc<scc::HashMap<K, Arc<watch::Sender<Option<Progress>>>>>,
+    /// Decide which transitions of the settled states are allowed.
+    transitions: Arc<dyn TransitionTable<K, S>>,
+    /// The states of the `Revoking` tasks before revoking, restored if the revoking fails.
+    revoking_from: Arc<scc::HashMap<K, S>>,
+    /// The revokings waiting for the `Working` tasks they took over.
+    pending_revokes: Arc<scc::HashMap<K, PendingRevoke<K, S>>>,
+    /// What the task becomes when its revoking fails.
+    revoke_failure_policy: RevokeFailurePolicy,
+    /// The errors of the last revokings executed asynchronously.
+    revoke_errors: Arc<scc::HashMap<K, RevokeError>>,
+    /// The compensations produced by the succeeded tasks.
+    compensations: Arc<scc::HashMap<K, Compensation>>,
 }
 
-/// Public interfaces.
 impl<K> AsyncTasksRecorder<K>
     where K: Eq + Hash + Clone + Send + Sync + 'static {
     /// Create a completely new `AsyncTasksRecoder`.
+    ///
+    /// Use [`default`](Self::default) to record the states other than [`TaskState`].
     pub fn new() -> Self {
-        AsyncTasksRecorder {
-            recorder: scc::HashMap::new().into(),
-        }
+        Self::default()
     }
+}
 
+/// Public interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
     /// Create by a map.
-    pub fn new_with_task_manager(recorder: scc::HashMap<K, TaskState>) -> Self {
-        AsyncTasksRecorder {
-            recorder: recorder.into(),
-        }
+    pub fn new_with_task_manager(recorder: scc::HashMap<K, S>) -> Self {
+        Self::new_with_task_manager_arc(recorder.into())
     }
 
     /// Create by an `Arc` of map.
-    pub fn new_with_task_manager_arc(recorder: Arc<scc::HashMap<K, TaskState>>) -> Self {
+    pub fn new_with_task_manager_arc(recorder: Arc<scc::HashMap<K, S>>) -> Self {
         AsyncTasksRecorder {
             recorder,
+            notifiers: scc::HashMap::new().into(),
+            cancellers: scc::HashMap::new().into(),
+            failures: scc::HashMap::new().into(),
+            attempts: scc::HashMap::new().into(),
+            panic_hook: None,
+            ttl: TtlConfig::default(),
+            finished_at: scc::HashMap::new().into(),
+            expire_hook: None,
+            expire_cleanup: None,
+            journal: None,
+            spawner: None,
+            events: broadcast::channel(DEFAULT_EVENT_CAPACITY).0,
+            observers: Arc::new(Vec::new()),
+            guards: Arc::new(Vec::new()),
+            guard_locks: scc::HashMap::new().into(),
+            admission: None,
+            priority_aging: None,
+            grouping: None,
+            dependencies: Default::default(),
+            progress: scc::HashMap::new().into(),
+            transitions: Arc::new(DefaultTransitions),
+            revoking_from: scc::HashMap::new().into(),
+            pending_revokes: scc::HashMap::new().into(),
+            revoke_failure_policy: RevokeFailurePolicy::default(),
+            revoke_errors: scc::HashMap::new().into(),
+            compensations: scc::HashMap::new().into(),
         }
     }
 
@@ -39,39 +180,23 @@ impl<K> AsyncTasksRecorder<K>
     ///
     /// Return **immediately**.
     ///
-    /// Can only launch successfully when the target task is `NotFound` or `Failed`.
-    /// Return `Err` when the state does not meet the requirements.
-    /// `Err` would include the task's current state.
+    /// Can only launch successfully when the target task is `NotFound` or `Failed`,
+    /// unless the rules are replaced by [`with_transition_table`](Self::with_transition_table).
+    /// Return `Err` when the state does not meet the requirements, a guard rejects, or a quota of its group is exceeded.
+    /// `Err` would include the task's current state, the rejection of the [`TransitionGuard`], or the exceeded [`Quota`].
     ///
     /// After `launch().await` returns `Ok`, the state of the task is at least `Working`.
-    pub async fn launch<Fut, R, E>(&self, task_id: K, task: Fut) -> Result<(), (TaskState, Fut)>
+    pub async fn launch<Fut, R, E>(&self, task_id: K, task: Fut) -> Result<(), (Refusal<S>, Fut)>
         where Fut: Future<Output=Result<R, E>> + Send + 'static,
               R: Send,
               E: Send {
-        let mut launch_flag = None;
-
-        self.recorder.entry_async(task_id.clone()).await
-            .and_modify(|v| {
-                if *v != TaskState::Failed {
-                    launch_flag = Some(v.clone());
-                    return;
-                }
-                *v = TaskState::Working;
-            })
-            // not found
-            .or_insert_with(|| {
-                TaskState::Working
-            });
-
-        if let Some(reason) = launch_flag {
-            return Err((reason, task));
-        }
+        let ticket = match self.try_mark_working(&task_id).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
 
         // start
-        let recorder = self.recorder.clone();
-        tokio::spawn(async move {
-            let _ = Self::launch_task_fut(&recorder, task_id, task).await;
-        });
+        self.spawn_launched_task(task_id, task, ticket);
 
         Ok(())
     }
@@ -80,43 +205,59 @@ impl<K> AsyncTasksRecorder<K>
     ///
     /// Not return (keep awaiting) until the task finishes when successfully launch.
     ///
-    /// Can only launch successfully when the target task is `NotFound` or `Failed`.
-    /// **Immediately** return `Err` when the state does not meet the requirements.
-    /// `Err` would include the task's current state.
-    pub async fn launch_block<Fut, R, E>(&self, task_id: K, task: Fut) -> Result<Result<R, E>, (TaskState, Fut)>
+    /// Can only launch successfully when the target task is `NotFound` or `Failed`,
+    /// unless the rules are replaced by [`with_transition_table`](Self::with_transition_table).
+    /// **Immediately** return `Err` when the state does not meet the requirements, a guard rejects, or a quota of its group is exceeded.
+    /// `Err` would include the task's current state, the rejection of the [`TransitionGuard`], or the exceeded [`Quota`].
+    ///
+    /// Return `Ok(None)` if the task is aborted by [`abort_task`](Self::abort_task) before it finishes.
+    pub async fn launch_block<Fut, R, E>(&self, task_id: K, task: Fut) -> Result<Option<Result<R, E>>, (Refusal<S>, Fut)>
         where Fut: Future<Output=Result<R, E>> + Send + 'static,
               R: Send,
               E: Send {
-        let mut launch_flag = None;
-
-        self.recorder.entry_async(task_id.clone()).await
-            .and_modify(|v| {
-                if *v != TaskState::Failed {
-                    launch_flag = Some(v.clone());
-                    return;
-                }
-                *v = TaskState::Working;
-            })
-            // not found
-            .or_insert(TaskState::Working);
-
-        if let Some(reason) = launch_flag {
-            return Err((reason, task));
-        }
+        let ticket = match self.try_mark_working(&task_id).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
 
         // start (block)
-        Self::launch_task_fut(&self.recorder, task_id, task).await
+        match self.launch_task_fut(task_id, task, ticket, |_| ()).await {
+            Ok(res) => Ok(res),
+            Err(payload) => resume_unwind(payload),
+        }
     }
 
     /// Query the target task's state.
-    pub async fn query_task_state<Q>(&self, task_id: &Q) -> TaskState
+    ///
+    /// An expired task would be removed and regarded as `NotFound`.
+    pub async fn query_task_state<Q>(&self, task_id: &Q) -> S
         where K: Borrow<Q>,
               Q: Hash + Eq + ?Sized {
         let res = self.recorder.get_async(task_id).await;
-        match res {
+        let state = match res {
             Some(res) => res.get().clone(),
-            None => TaskState::NotFound,
+            None => return S::not_found(),
+        };
+
+        if self.is_expired(task_id, &state) && self.remove_if_expired(task_id).await {
+            return S::not_found();
+        }
+        state
+    }
+
+    /// Query the reason why the target task is `Failed` or `RevokeFailed`,
+    /// or in the state decided by [`TransitionTable::failed_state`].
+    ///
+    /// Return `None` if the task did not fail (or fail to be revoked) in its last run,
+    /// or it was made so by [`modify_state_force`](Self::modify_state_force).
+    pub async fn query_failure_reason<Q>(&self, task_id: &Q) -> Option<FailureReason>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let state = self.query_task_state(task_id).await;
+        if state == S::not_found() || is_in_progress(&state) {
+            return None;
         }
+        self.failures.read_async(task_id, |_, v| v.clone()).await
     }
 
     /// Revoke target task with its `task_id` and a `Future` for revoking,  and execute it asynchronously.
@@ -124,32 +265,21 @@ impl<K> AsyncTasksRecorder<K>
     /// Return **immediately**.
     ///
     /// If the target task is not `Success` (perhaps it is being revoked by another thread),
+    /// or not allowed by [`with_transition_table`](Self::with_transition_table),
     /// then this method would return `Err`.
-    /// `Err` would include the task's current state.
-    pub async fn revoke_task<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut) -> Result<(), (TaskState, Fut)>
+    /// `Err` would include the task's current state, or the rejection of the [`TransitionGuard`].
+    pub async fn revoke_task<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut) -> Result<(), (Refusal<S>, Fut)>
         where K: Borrow<Q>,
-              Q: Hash + Eq + ?Sized + Clone + Send + Sync + 'static,
+              Q: Hash + Eq + Clone + Send + Sync + 'static,
               Fut: Future<Output=Result<R, E>> + Send + 'static,
               R: Send,
               E: Send {
-        let ent = self.recorder.get_async(target_task_id).await;
-        match ent {
-            Some(mut ent) => {
-                let state = ent.get_mut();
-                if *state != TaskState::Success {
-                    return Err((state.clone(), revoke_task));
-                }
-                *state = TaskState::Revoking;
-            }
-            None => return Err((TaskState::NotFound, revoke_task)),
-        };
+        if let Err(reason) = self.try_mark_revoking(target_task_id).await {
+            return Err((reason, revoke_task));
+        }
 
         // start to revoke
-        let recorder = self.recorder.clone();
-        let target_task_id = target_task_id.clone();
-        tokio::spawn(async move {
-            let _ = Self::revoke_task_fut(&recorder, &target_task_id, revoke_task).await;
-        });
+        self.spawn_revoking_task(target_task_id.clone(), revoke_task, None);
 
         Ok(())
     }
@@ -159,28 +289,25 @@ impl<K> AsyncTasksRecorder<K>
     /// Not return (keep awaiting) until the task finishes when successfully start to revoke.
     ///
     /// If the target task is not `Success` (perhaps it is being revoked by another thread),
+    /// or not allowed by [`with_transition_table`](Self::with_transition_table),
     /// then this method would return `Err` immediately.
-    /// `Err` would include the task's current state.
-    pub async fn revoke_task_block<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut) -> Result<Result<R, E>, (TaskState, Fut)>
+    /// `Err` would include the task's current state, or the rejection of the [`TransitionGuard`].
+    pub async fn revoke_task_block<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut) -> Result<Result<R, E>, (Refusal<S>, Fut)>
         where K: Borrow<Q>,
               Q: Hash + Eq + ?Sized,
               Fut: Future<Output=Result<R, E>> + Send + 'static,
               R: Send,
               E: Send {
-        let ent = self.recorder.get_async(target_task_id).await;
-        match ent {
-            Some(mut ent) => {
-                let state = ent.get_mut();
-                if *state != TaskState::Success {
-                    return Err((state.clone(), revoke_task));
-                }
-                *state = TaskState::Revoking;
-            }
-            None => return Err((TaskState::NotFound, revoke_task)),
-        };
+        if let Err(reason) = self.try_mark_revoking(target_task_id).await {
+            return Err((reason, revoke_task));
+        }
 
         // start to revoke (block)
-        Self::revoke_task_fut(&self.recorder, target_task_id, revoke_task).await
+        match self.revoke_task_fut(target_task_id, revoke_task, None, |_| None).await {
+            Ok(Some(res)) => Ok(res),
+            Ok(None) => unreachable!("revoking without deadline is timed out"),
+            Err(payload) => resume_unwind(payload),
+        }
     }
 
     /// Modify task's state atomically and forcefully. Not usually used.
@@ -188,15 +315,35 @@ impl<K> AsyncTasksRecorder<K>
     /// This method may break business, especially during revoking.
     ///
     /// If `target_state == TaskState::NotFound`, the `target_task_id` would be removed from the map.
-    pub async fn modify_state_force(&self, target_task_id: K, target_state: TaskState) {
-        if target_state == TaskState::NotFound {
-            self.recorder.remove_async(&target_task_id).await;
+    pub async fn modify_state_force(&self, target_task_id: K, target_state: S) {
+        self.clear_run_records(&target_task_id);
+        if target_state == S::not_found() {
+            self.discard_compensation(&target_task_id);
+            // the event is sent while the entry is locked
+            match self.recorder.entry_async(target_task_id.clone()).await {
+                Entry::Occupied(ent) => {
+                    self.emit_transition(ent.key(), ent.get().clone(), target_state, TransitionCause::Forced);
+                    let (task_id, _) = ent.remove_entry();
+                    self.on_entry_removed(&task_id);
+                }
+                Entry::Vacant(ent) => {
+                    self.emit_transition(ent.key(), S::not_found(), target_state, TransitionCause::Forced);
+                }
+            }
+            self.on_state_changed(&target_task_id);
             return;
         }
 
-        self.recorder.entry_async(target_task_id).await
-            .and_modify(|v| *v = target_state.clone())
-            .or_insert(target_state);
+        let mut old_state = S::not_found();
+        let ent = self.recorder.entry_async(target_task_id.clone()).await
+            .and_modify(|v| old_state = std::mem::replace(v, target_state.clone()))
+            .or_insert(target_state.clone());
+        self.emit_transition(ent.key(), old_state.clone(), target_state, TransitionCause::Forced);
+        drop(ent);
+        if old_state == S::not_found() {
+            self.on_entry_inserted(&target_task_id);
+        }
+        self.on_state_changed(&target_task_id);
     }
 
     /// Change task's state to `Success` atomically when task is `NotFound` or `Failed`.
@@ -205,91 +352,537 @@ impl<K> AsyncTasksRecorder<K>
     ///
     /// - Return `Ok(task_state)` if succeed and the task was in `task_state` state.
     /// - Return `Err(task_state)` if failed and the task was in `task_state` state.
-    pub async fn modify_to_success_before_work(&self, target_task_id: K) -> Result<TaskState, TaskState> {
-        let mut res: Result<TaskState, TaskState> = Ok(TaskState::NotFound);
+    pub async fn modify_to_success_before_work(&self, target_task_id: K) -> Result<S, S> {
+        let mut res: Result<S, S> = Ok(S::not_found());
 
-        self.recorder.entry_async(target_task_id).await
+        let ent = self.recorder.entry_async(target_task_id.clone()).await
             .and_modify(|v| {
-                if *v != TaskState::Failed {
+                if *v != S::failed() {
                     res = Err(v.clone());
                     return;
                 }
-                *v = TaskState::Success;
-                res = Ok(TaskState::Failed);
+                self.clear_run_records(&target_task_id);
+                *v = S::success();
+                res = Ok(S::failed());
             })
             // not found
-            .or_insert(TaskState::Success);
+            .or_insert(S::success());
+        if let Ok(old_state) = &res {
+            self.emit_transition(ent.key(), old_state.clone(), S::success(), TransitionCause::Forced);
+        }
+        drop(ent);
 
+        if let Ok(old_state) = &res {
+            if *old_state == S::not_found() {
+                self.on_entry_inserted(&target_task_id);
+            }
+            self.on_state_changed(&target_task_id);
+        }
         res
     }
 
     /// Get a reference of the internal map.
-    pub fn get_recorder_ref(&self) -> &scc::HashMap<K, TaskState> {
+    pub fn get_recorder_ref(&self) -> &scc::HashMap<K, S> {
         &self.recorder
     }
 
     /// Get an cloned `Arc` of the internal map.
-    pub fn get_recorder_arc(&self) -> Arc<scc::HashMap<K, TaskState>> {
+    pub fn get_recorder_arc(&self) -> Arc<scc::HashMap<K, S>> {
         self.recorder.clone()
     }
 }
 
-impl<K> Default for AsyncTasksRecorder<K>
-    where K: Eq + Hash + Clone + Send + Sync + 'static {
+impl<K, S> Default for AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
     fn default() -> Self {
-        AsyncTasksRecorder::new()
+        Self::new_with_task_manager_arc(scc::HashMap::new().into())
     }
 }
 
-/// Private tools.
-impl<K> AsyncTasksRecorder<K>
-    where K: Eq + Hash + Clone + Send + Sync + 'static {
-    /// The async function to execute launched tasks.
-    async fn launch_task_fut<Fut, R, E>(
-        recorder: &scc::HashMap<K, TaskState>,
-        task_id: K, task: Fut)
-        -> Result<Result<R, E>, (TaskState, Fut)>
+impl<K, S> std::fmt::Debug for AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + std::fmt::Debug + 'static,
+          S: RecordedState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncTasksRecorder")
+            .field("recorder", &self.recorder)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Crate-level tools.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Change the task's state to `Working` (or `Queued` if the concurrency limit is reached) atomically
+    /// when the [`TransitionTable`] and the guards allow it.
+    ///
+    /// Return the ticket which should be passed to [`launch_task_fut`](Self::launch_task_fut).
+    /// Return `Err` when the state does not meet the requirements, a guard rejects, or a quota of its group is exceeded.
+    pub(crate) async fn try_mark_working(&self, task_id: &K) -> Result<LaunchTicket, Refusal<S>> {
+        self.try_mark_working_with(task_id, LaunchOptions::default()).await
+    }
+
+    /// Same as [`try_mark_working`](Self::try_mark_working), but the task is launched as `options` says.
+    pub(crate) async fn try_mark_working_with(&self, task_id: &K, mut options: LaunchOptions<K>) -> Result<LaunchTicket, Refusal<S>> {
+        // the time waiting for the guards is included
+        let deadline = options.deadline();
+        let prerequisites = options.take_prerequisites();
+        let _guard_lock = match self.guards.is_empty() {
+            true => None,
+            false => Some(self.lock_for_guards(task_id.clone()).await),
+        };
+        // the state which the guards allow launching from
+        let mut checked_state = None;
+        let (entry, old_state, expired_state) = loop {
+            let entry = self.recorder.entry_async(task_id.clone()).await;
+
+            // an expired task is regarded as `NotFound`
+            let mut expired_state = None;
+            let old_state = match &entry {
+                Entry::Occupied(ent) if self.is_expired(task_id, ent.get()) => {
+                    expired_state = Some(ent.get().clone());
+                    S::not_found()
+                }
+                Entry::Occupied(ent) => ent.get().clone(),
+                Entry::Vacant(_) => S::not_found(),
+            };
+            if !self.can_launch_from(task_id, &old_state) {
+                return Err(Refusal::State(old_state));
+            }
+            // the guards are checked without locking the entry,
+            // and checked again if the state is changed meanwhile
+            if !self.guards.is_empty() && checked_state.as_ref() != Some(&old_state) {
+                drop(entry);
+                self.check_launch_guards(task_id, &old_state).await?;
+                checked_state = Some(old_state);
+                continue;
+            }
+            break (entry, old_state, expired_state);
+        };
+
+        let registration = match prerequisites.is_empty() {
+            true => None,
+            false => Some(self.register_dependencies(task_id, &prerequisites).ok_or(Refusal::DependencyCycle)?),
+        };
+        let group = self.group_of(task_id);
+        let slot = self.admit(options.priority(), group, matches!(entry, Entry::Vacant(_)), registration.is_some())
+            .map_err(Refusal::QuotaExceeded)?;
+
+        // the canceller is registered while the entry is locked,
+        // so a `Working` task can always be found by `abort_task`
+        let (canceller, cancel_rx) = Canceller::new();
+        let run_id = canceller.run_id;
+        self.clear_run_records(task_id);
+        self.discard_compensation(task_id);
+        self.cancellers.upsert(task_id.clone(), canceller);
+        let state = match slot {
+            Slot::Queued(_) | Slot::Deferred { .. } => S::queued(),
+            _ => S::working(),
+        };
+        let ent = match entry {
+            Entry::Occupied(mut ent) => {
+                *ent.get_mut() = state.clone();
+                ent
+            }
+            Entry::Vacant(ent) => ent.insert_entry(state.clone()),
+        };
+        self.observe(|o| o.on_launch(task_id, &old_state));
+        if let Some(expired_state) = &expired_state {
+            self.emit_expired(task_id, expired_state.clone());
+        }
+        self.emit_transition(task_id, old_state, state, TransitionCause::Launched);
+        drop(ent);
+
+        if let Some(expired_state) = expired_state {
+            self.on_expired(task_id, expired_state);
+        }
+        self.on_state_changed(task_id);
+        Ok(LaunchTicket {
+            run_id,
+            cancel_rx,
+            slot,
+            dependencies: registration.map(|registration| self.wait_dependencies(registration, prerequisites)),
+            deadline,
+        })
+    }
+
+    /// Change the task's state to `Revoking` atomically when the [`TransitionTable`] and the guards allow it.
+    ///
+    /// Return `Err` when the state does not meet the requirements or a guard rejects.
+    pub(crate) async fn try_mark_revoking<Q>(&self, target_task_id: &Q) -> Result<(), Refusal<S>>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.try_mark_revoking_with(target_task_id, |_| Ok(())).await
+    }
+
+    /// Same as [`try_mark_revoking`](Self::try_mark_revoking),
+    /// but `prepare` is called while the entry is locked, and the task is not revoked if it returns `Err`.
+    pub(crate) async fn try_mark_revoking_with<Q, T, F>(&self, target_task_id: &Q, prepare: F) -> Result<T, Refusal<S>>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized,
+              F: FnOnce(&K) -> Result<T, Refusal<S>> {
+        let mut guard_lock = None;
+        let mut checked = false;
+        let mut ent = loop {
+            let Some(ent) = self.recorder.get_async(target_task_id).await else {
+                return Err(Refusal::State(S::not_found()));
+            };
+            if !self.can_revoke_from(ent.key(), ent.get()) {
+                return Err(Refusal::State(ent.get().clone()));
+            }
+            // the guards are checked without locking the entry,
+            // and the state is checked again afterwards
+            if !self.guards.is_empty() && !checked {
+                let task_id = ent.key().clone();
+                drop(ent);
+                match guard_lock {
+                    None => guard_lock = Some(self.lock_for_guards(task_id).await),
+                    Some(_) => {
+                        self.check_revoke_guards(&task_id).await?;
+                        checked = true;
+                    }
+                }
+                continue;
+            }
+            break ent;
+        };
+        let prepared = prepare(ent.key())?;
+
+        let old_state = std::mem::replace(ent.get_mut(), S::revoking());
+        if old_state != S::success() {
+            self.revoking_from.upsert(ent.key().clone(), old_state.clone());
+        }
+        self.observe(|o| o.on_revoke_start(ent.key()));
+        self.emit_transition(ent.key(), old_state, S::revoking(), TransitionCause::RevokeStarted);
+        drop(ent);
+        drop(guard_lock);
+
+        self.on_state_changed(target_task_id);
+        Ok(prepared)
+    }
+
+    /// Execute a task which has been marked as `Working` asynchronously.
+    pub(crate) fn spawn_launched_task<Fut, R, E>(&self, task_id: K, task: Fut, ticket: LaunchTicket)
         where Fut: Future<Output=Result<R, E>> + Send + 'static,
               R: Send,
               E: Send {
-        // execute task
-        let task_res = task.await;
+        let recorder = self.clone();
+        self.spawn(async move {
+            if let Err(payload) = recorder.launch_task_fut(task_id.clone(), task, ticket, |_| ()).await {
+                recorder.handle_panic(&task_id, payload);
+            }
+        });
+    }
+
+    /// Execute a `Future` for revoking a task which has been marked as `Revoking` asynchronously.
+    ///
+    /// The error of the `Future` is recorded, see [`query_revoke_error`](Self::query_revoke_error).
+    pub(crate) fn spawn_revoking_task<Q, Fut, R, E>(&self, target_task_id: Q, revoke_task: Fut, deadline: Option<Instant>)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + Send + Sync + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let recorder = self.clone();
+        self.spawn(async move {
+            let revoke_task = async move {
+                wrap_revoke_error::<Fut>(revoke_task.await)
+            };
+            let res = recorder.revoke_task_fut(&target_task_id, revoke_task, deadline, |e| Some(e.clone())).await;
+            if let Err(payload) = res {
+                recorder.handle_panic(&target_task_id, payload);
+            }
+        });
+    }
+
+    /// The async function to execute launched tasks.
+    ///
+    /// See [`run_task_fut`](Self::run_task_fut).
+    ///
+    /// - Return `Ok(None)` if the task is aborted, timed out or its prerequisites fail.
+    /// - Return `Err(payload)` if the task panics.
+    pub(crate) async fn launch_task_fut<Fut, R, E, F>(
+        &self,
+        task_id: K, task: Fut,
+        ticket: LaunchTicket,
+        on_finish: F)
+        -> Result<Option<Result<R, E>>, PanicPayload>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send,
+              F: FnOnce(&Result<R, E>) {
+        match self.run_task_fut(task_id, task, ticket, on_finish).await {
+            Ok(output) => Ok(Some(Ok(output))),
+            Err(TaskError::Error(e)) => Ok(Some(Err(e))),
+            Err(TaskError::Panicked(payload)) => Err(payload),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The async function to execute launched tasks, telling why the task does not succeed.
+    ///
+    /// A `Queued` task is started when its prerequisites succeed and it gets a slot.
+    ///
+    /// `on_finish` is called with the output before the state is changed,
+    /// unless the task is aborted, timed out or its prerequisites fail.
+    ///
+    /// The task would become `Failed` if it panics or this `Future` is dropped before the task finishes.
+    pub(crate) async fn run_task_fut<Fut, R, E, F>(
+        &self,
+        task_id: K, task: Fut,
+        ticket: LaunchTicket,
+        on_finish: F)
+        -> Result<R, TaskError<E>>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send,
+              F: FnOnce(&Result<R, E>) {
+        let drop_guard = DropGuard::new(self, &task_id, self.failed_state(&task_id, &FailureReason::Dropped),
+                                        Some(FailureReason::Dropped));
+
+        // wait for the prerequisites and a slot,
+        // and then execute task until it finishes, panics, is aborted or timed out
+        let LaunchTicket { run_id, mut cancel_rx, mut slot, mut dependencies, deadline } = ticket;
+        let mut task = pin!(task);
+        let mut timeout = pin!(self.sleep_until_deadline(deadline));
+        let mut abort_ack = None;
+        let mut dependency_failed = false;
+        let task_res = poll_fn(|cx| {
+            if let Some(Poll::Ready(satisfied)) = dependencies.as_mut().map(|fut| fut.as_mut().poll(cx)) {
+                dependencies = None;
+                if !satisfied {
+                    dependency_failed = true;
+                    return Poll::Ready(Ok(None));
+                }
+                if let Slot::Deferred { priority, group } = slot {
+                    slot = self.admit_deferred(priority, group);
+                    if !matches!(slot, Slot::Queued(_)) {
+                        self.mark_started(&task_id);
+                    }
+                }
+            }
+            if let Slot::Queued(permit_rx) = &mut slot {
+                if let Poll::Ready(permit) = Pin::new(permit_rx).poll(cx) {
+                    slot = permit.map_or(Slot::Unlimited, Slot::Acquired);
+                    self.mark_started(&task_id);
+                }
+            }
+            if !matches!(slot, Slot::Queued(_) | Slot::Deferred { .. }) {
+                match catch_unwind(AssertUnwindSafe(|| task.as_mut().poll(cx))) {
+                    Ok(Poll::Ready(res)) => return Poll::Ready(Ok(Some(res))),
+                    Ok(Poll::Pending) => {}
+                    Err(payload) => return Poll::Ready(Err(payload)),
+                }
+            }
+            if let Poll::Ready(ack) = Pin::new(&mut cancel_rx).poll(cx) {
+                abort_ack = Some(ack);
+                return Poll::Ready(Ok(None));
+            }
+            match timeout.as_mut().poll(cx) {
+                Poll::Ready(()) => Poll::Ready(Ok(None)),
+                Poll::Pending => Poll::Pending,
+            }
+        }).await;
+
+        // whoever removes the canceller first decides whether the task is aborted
+        let aborted = self.cancellers.remove_if_async(&task_id, |canceller| canceller.run_id == run_id).await.is_none();
+        let task_res = match aborted {
+            true => task_res.map(|_| None),
+            false => task_res,
+        };
 
         // handle result
-        if task_res.is_ok() {
-            recorder.update_async(
-                &task_id,
-                |_, v| *v = TaskState::Success)
-                .await.unwrap();
-        } else {
-            recorder.update_async(
-                &task_id,
-                |_, v| *v = TaskState::Failed)
-                .await.unwrap();
+        let failure = match &task_res {
+            Ok(Some(res)) => {
+                on_finish(res);
+                res.as_ref().err().map(|_| FailureReason::Error)
+            }
+            Ok(None) if aborted => Some(FailureReason::Aborted),
+            Ok(None) if dependency_failed => Some(FailureReason::DependencyFailed),
+            Ok(None) => Some(FailureReason::TimedOut),
+            Err(_) => Some(FailureReason::Panicked),
+        };
+        let (state, cause) = match failure {
+            Some(failure) => {
+                self.failures.upsert_async(task_id.clone(), failure.clone()).await;
+                (self.failed_state(&task_id, &failure), TransitionCause::Failed(failure))
+            }
+            None => (self.succeeded_state(&task_id), TransitionCause::Succeeded),
+        };
+        self.clear_progress(&task_id);
+        let changed = self.recorder.update_async(&task_id, |k, v| {
+            // taken over by a revoking, see `revoke_task_even_working`
+            if *v == S::revoking() {
+                return None;
+            }
+            let old_state = std::mem::replace(v, state.clone());
+            match &cause {
+                TransitionCause::Failed(failure) => self.observe(|o| o.on_failure(k, failure)),
+                _ => self.observe(|o| o.on_success(k)),
+            }
+            self.emit_transition(k, old_state, state.clone(), cause.clone());
+            Some(())
+        }).await.unwrap();
+        drop_guard.disarm();
+        match changed {
+            Some(()) => self.on_state_changed(&task_id),
+            None => self.finish_taken_over(&task_id, matches!(cause, TransitionCause::Succeeded)),
+        }
+        // release the slot after the task finishes
+        drop(slot);
+
+        if aborted {
+            // the ack may be not received yet if the task finished at the same time
+            let abort_ack = match abort_ack {
+                Some(abort_ack) => abort_ack,
+                None => cancel_rx.await,
+            };
+            if let Ok(abort_ack) = abort_ack {
+                let _ = abort_ack.send(());
+            }
         }
 
-        Ok(task_res)
+        match task_res {
+            Ok(Some(Ok(output))) => Ok(output),
+            Ok(Some(Err(e))) => Err(TaskError::Error(e)),
+            Ok(None) if aborted => Err(TaskError::Aborted),
+            Ok(None) if dependency_failed => Err(TaskError::DependencyFailed),
+            Ok(None) => Err(TaskError::TimedOut),
+            Err(payload) => Err(TaskError::Panicked(payload)),
+        }
     }
 
     /// The async function to execute `Future` to revoke a task.
-    async fn revoke_task_fut<Q, Fut, R, E>(
-        recorder: &scc::HashMap<K, TaskState>,
-        target_task_id: &Q, revoke_task: Fut)
-        -> Result<Result<R, E>, (TaskState, Fut)>
+    ///
+    /// `record_error` decides what is recorded as the revoke error if the `Future` returns `Err`.
+    /// The record is removed if it returns `None`, or the revoking fails in other ways.
+    ///
+    /// - Return `Ok(None)` if the `Future` is timed out.
+    /// - Return `Err(payload)` if the `Future` panics.
+    ///
+    /// The task would be back to its state before revoking (usually `Success`),
+    /// or changed by the [`RevokeFailurePolicy`], if the `Future` fails, panics, is timed out
+    /// or this `Future` is dropped before the `Future` finishes.
+    pub(crate) async fn revoke_task_fut<Q, Fut, R, E, F>(
+        &self,
+        target_task_id: &Q, revoke_task: Fut,
+        deadline: Option<Instant>,
+        record_error: F)
+        -> Result<Option<Result<R, E>>, PanicPayload>
         where K: Borrow<Q>,
               Q: Hash + Eq + ?Sized,
               Fut: Future<Output=Result<R, E>> + Send + 'static,
               R: Send,
-              E: Send {
-        let revoke_res = revoke_task.await;
-
-        if revoke_res.is_ok() {
-            recorder.remove_async(target_task_id).await;
+              E: Send,
+              F: FnOnce(&E) -> Option<RevokeError> {
+        let restored_state = self.revoking_from.remove_async(target_task_id).await
+            .map_or(S::success(), |(_, state)| state);
+        let (failed_state, keeps_reason) = self.revoke_failed_state(restored_state);
+        let drop_guard = DropGuard::revoking(self, target_task_id, failed_state.clone(),
+                                             keeps_reason.then_some(FailureReason::Dropped));
+
+        let mut revoke_task = pin!(revoke_task);
+        let mut timeout = pin!(self.sleep_until_deadline(deadline));
+        let revoke_res = poll_fn(|cx| {
+            match catch_unwind(AssertUnwindSafe(|| revoke_task.as_mut().poll(cx))) {
+                Ok(Poll::Ready(res)) => return Poll::Ready(Ok(Some(res))),
+                Ok(Poll::Pending) => {}
+                Err(payload) => return Poll::Ready(Err(payload)),
+            }
+            match timeout.as_mut().poll(cx) {
+                Poll::Ready(()) => Poll::Ready(Ok(None)),
+                Poll::Pending => Poll::Pending,
+            }
+        }).await;
+
+        if matches!(revoke_res, Ok(Some(Ok(_)))) {
+            self.clear_run_records(target_task_id);
+            self.discard_compensation(target_task_id);
+            let removed = match self.recorder.get_async(target_task_id).await {
+                Some(ent) => {
+                    self.observe(|o| o.on_revoked(ent.key()));
+                    self.emit_transition(ent.key(), S::revoking(), S::not_found(), TransitionCause::Revoked);
+                    Some(ent.remove_entry())
+                }
+                None => None,
+            };
+            if let Some((task_id, _)) = removed {
+                self.on_entry_removed(&task_id);
+            }
         } else {
-            recorder.update_async(target_task_id,
-                                  |_, v| *v = TaskState::Success).await;
+            let failure = match &revoke_res {
+                Ok(Some(_)) => FailureReason::Error,
+                Ok(None) => FailureReason::TimedOut,
+                Err(_) => FailureReason::Panicked,
+            };
+            let error = match &revoke_res {
+                Ok(Some(Err(e))) => record_error(e),
+                _ => None,
+            };
+            self.recorder.update_async(target_task_id, |k, v| {
+                if keeps_reason {
+                    self.failures.upsert(k.clone(), failure);
+                }
+                match error {
+                    Some(error) => {
+                        self.revoke_errors.upsert(k.clone(), error);
+                    }
+                    None => {
+                        self.revoke_errors.remove(k);
+                    }
+                }
+                *v = failed_state.clone();
+                self.observe(|o| o.on_revoke_failed(k));
+                self.emit_transition(k, S::revoking(), failed_state, TransitionCause::RevokeFailed);
+            }).await;
         }
+        drop_guard.disarm();
+        self.on_state_changed(target_task_id);
 
-        Ok(revoke_res)
+        revoke_res
+    }
+
+    /// Change the task's state from `Queued` to `Working` when it gets a slot.
+    fn mark_started(&self, task_id: &K) {
+        let started = self.recorder.update(task_id, |k, v| {
+            // may have been taken over by a revoking
+            if *v != S::queued() {
+                return false;
+            }
+            *v = S::working();
+            self.observe(|o| o.on_start(k));
+            self.emit_transition(k, S::queued(), S::working(), TransitionCause::Started);
+            true
+        });
+        if started == Some(true) {
+            self.on_state_changed(task_id);
+        }
+    }
+
+    /// Remove the records about the last run of the target task, e.g. its failure reason.
+    ///
+    /// Should be called before the task is launched or removed.
+    /// The compensation is kept until [`discard_compensation`](Self::discard_compensation) is called.
+    pub(crate) fn clear_run_records<Q>(&self, task_id: &Q)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.failures.remove(task_id);
+        self.attempts.remove(task_id);
+        self.pending_revokes.remove(task_id);
+        self.revoke_errors.remove(task_id);
+    }
+
+    /// Update the records depending on the state of the target task,
+    /// and wake up all the callers waiting for it.
+    ///
+    /// Should be called after each change of the task's state.
+    pub(crate) fn on_state_changed<Q>(&self, task_id: &Q)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        if self.ttl.is_enabled() {
+            self.update_finished_at(task_id);
+        }
+        self.notifiers.read(task_id, |_, v| v.notify_waiters());
     }
 }
@@ -0,0 +1,79 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::oneshot;
+use crate::*;
+
+/// The id of the next run of any task.
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Send an acknowledgement sender to abort a run of a `Working` or `Queued` task.
+/// The acknowledgement is sent after the aborted task becomes `Failed`.
+#[derive(Debug)]
+pub(crate) struct Canceller {
+    /// Tell apart the runs of the same task.
+    pub(crate) run_id: u64,
+    tx: oneshot::Sender<oneshot::Sender<()>>,
+}
+
+/// The receiving side of [`Canceller`].
+pub(crate) type CancelReceiver = oneshot::Receiver<oneshot::Sender<()>>;
+
+impl Canceller {
+    /// Create for a new run.
+    pub(crate) fn new() -> (Self, CancelReceiver) {
+        let (tx, rx) = oneshot::channel();
+        let canceller = Canceller {
+            run_id: NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed),
+            tx,
+        };
+        (canceller, rx)
+    }
+
+    /// Abort the run, and wait until its state has been changed.
+    pub(crate) async fn cancel(self) {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        if self.tx.send(ack_tx).is_ok() {
+            // `Err` means the task has been dropped, e.g. the runtime is shutting down
+            let _ = ack_rx.await;
+        }
+    }
+}
+
+/// Aborting interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Abort a `Working` or `Queued` task, and make it `Failed`.
+    ///
+    /// Not return until the state of the aborted task has been changed.
+    /// The `Future` of the aborted task would be dropped without being polled again,
+    /// and the corresponding [`launch_block`](Self::launch_block) would return `Ok(None)`.
+    ///
+    /// - Return `Ok(())` if the task is aborted before it finishes.
+    ///   Its output would be discarded even if it finishes at the same time.
+    /// - Return `Err(task_state)` if the task is not `Working` or `Queued` (e.g. it has finished),
+    ///   and the task was in `task_state` state.
+    pub async fn abort_task<Q>(&self, task_id: &Q) -> Result<(), S>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        match self.cancellers.remove_async(task_id).await {
+            Some((_, canceller)) => {
+                canceller.cancel().await;
+                Ok(())
+            }
+            None => Err(self.query_task_state(task_id).await),
+        }
+    }
+
+    /// Same as [`abort_task`](Self::abort_task), but only abort the run of `run_id`.
+    pub(crate) async fn abort_run(&self, task_id: &K, run_id: u64) -> Result<(), S> {
+        match self.cancellers.remove_if_async(task_id, |canceller| canceller.run_id == run_id).await {
+            Some((_, canceller)) => {
+                canceller.cancel().await;
+                Ok(())
+            }
+            None => Err(self.query_task_state(task_id).await),
+        }
+    }
+}
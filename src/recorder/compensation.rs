@@ -0,0 +1,178 @@
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::panic::resume_unwind;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::*;
+use super::wrap_revoke_error;
+
+/// The `Future` for revoking created by a [`Compensation`], whose error is wrapped by [`RevokeError`].
+pub type CompensationFuture = Pin<Box<dyn Future<Output=Result<(), RevokeError>> + Send + 'static>>;
+
+/// How to undo a task, produced by the task itself when it succeeds.
+///
+/// Returned by a task launched by [`AsyncTasksRecorder::launch_with_compensation`],
+/// and executed by [`revoke`](AsyncTasksRecorder::revoke).
+/// It can be executed again if the revoking fails.
+#[derive(Clone)]
+pub struct Compensation(Arc<dyn Fn() -> CompensationFuture + Send + Sync + 'static>);
+
+impl Compensation {
+    /// Create by a function creating the `Future` for revoking.
+    ///
+    /// The output is discarded, and the error can be got back by [`RevokeError::with_downcast`].
+    pub fn new<F, Fut, R, E>(revoke_task_factory: F) -> Self
+        where F: Fn() -> Fut + Send + Sync + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              E: Send {
+        Compensation(Arc::new(move || {
+            let revoke_task = revoke_task_factory();
+            Box::pin(async move {
+                wrap_revoke_error::<Fut>(revoke_task.await).map(|_| ())
+            })
+        }))
+    }
+
+    /// Create the `Future` for revoking.
+    fn create(&self) -> CompensationFuture {
+        (self.0)()
+    }
+}
+
+impl std::fmt::Debug for Compensation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Compensation").finish_non_exhaustive()
+    }
+}
+
+/// Compensation interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Launch a task which produces its own [`Compensation`] on success, and execute it asynchronously.
+    ///
+    /// Return **immediately**.
+    ///
+    /// Same as [`launch`](Self::launch), and the task is launched as `options` says, see [`LaunchOptions`].
+    /// The compensation is stored before the task becomes `Success`,
+    /// and can be executed by [`revoke`](Self::revoke) later.
+    /// It is kept until the task is revoked successfully, launched again or removed.
+    pub async fn launch_with_compensation<Fut, R, E>(&self, task_id: K, task: Fut, options: LaunchOptions<K>) -> Result<(), (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<(R, Compensation), E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+
+        // start
+        let recorder = self.clone();
+        self.spawn(async move {
+            let res = recorder.launch_task_fut(task_id.clone(), task, ticket, |res| {
+                recorder.store_compensation(&task_id, res);
+            }).await;
+            if let Err(payload) = res {
+                recorder.handle_panic(&task_id, payload);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Launch a task which produces its own [`Compensation`] on success.
+    ///
+    /// Not return (keep awaiting) until the task finishes when successfully launch.
+    ///
+    /// See [`launch_with_compensation`](Self::launch_with_compensation).
+    /// The compensation is not included in the output.
+    ///
+    /// Return `Ok(None)` if the task is aborted, timed out or any prerequisite fails.
+    pub async fn launch_block_with_compensation<Fut, R, E>(&self, task_id: K, task: Fut, options: LaunchOptions<K>) -> Result<Option<Result<R, E>>, (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<(R, Compensation), E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+
+        // start (block)
+        let res = self.launch_task_fut(task_id.clone(), task, ticket, |res| {
+            self.store_compensation(&task_id, res);
+        }).await;
+        match res {
+            Ok(res) => Ok(res.map(|res| res.map(|(output, _)| output))),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+
+    /// Revoke target task by the [`Compensation`] produced by itself, and execute it asynchronously.
+    ///
+    /// Return **immediately**.
+    ///
+    /// Same as [`revoke_task`](Self::revoke_task),
+    /// but `Err` would also be returned if the task has no compensation, i.e. it is not launched by
+    /// [`launch_with_compensation`](Self::launch_with_compensation) or [`launch_block_with_compensation`](Self::launch_block_with_compensation).
+    pub async fn revoke<Q>(&self, target_task_id: &Q) -> Result<(), Refusal<S>>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + Clone + Send + Sync + 'static {
+        let compensation = self.try_mark_revoking_with(target_task_id, |task_id| self.get_compensation(task_id)).await?;
+
+        // start to revoke, the error has been wrapped
+        let recorder = self.clone();
+        let task_id = target_task_id.clone();
+        self.spawn(async move {
+            // created when polled, so that a panicking factory is handled like a panicking revoking
+            let revoke_task = async move { compensation.create().await };
+            let res = recorder.revoke_task_fut(&task_id, revoke_task, None, |e| Some(e.clone())).await;
+            if let Err(payload) = res {
+                recorder.handle_panic(&task_id, payload);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Revoke target task by the [`Compensation`] produced by itself.
+    ///
+    /// Not return (keep awaiting) until the revoking finishes when successfully start to revoke.
+    ///
+    /// See [`revoke`](Self::revoke).
+    /// The error of the compensation is returned instead of recorded.
+    pub async fn revoke_block<Q>(&self, target_task_id: &Q) -> Result<Result<(), RevokeError>, Refusal<S>>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let compensation = self.try_mark_revoking_with(target_task_id, |task_id| self.get_compensation(task_id)).await?;
+
+        // start to revoke (block), created when polled like in `revoke`
+        let revoke_task = async move { compensation.create().await };
+        match self.revoke_task_fut(target_task_id, revoke_task, None, |_| None).await {
+            Ok(Some(res)) => Ok(res),
+            Ok(None) => unreachable!("revoking without deadline is timed out"),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+
+    /// Store the compensation produced by a succeeded task.
+    fn store_compensation<R, E>(&self, task_id: &K, res: &Result<(R, Compensation), E>) {
+        if let Ok((_, compensation)) = res {
+            self.compensations.upsert(task_id.clone(), compensation.clone());
+        }
+    }
+
+    /// Remove the compensation of the target task.
+    ///
+    /// Should be called before the task is launched or removed.
+    pub(crate) fn discard_compensation<Q>(&self, task_id: &Q)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.compensations.remove(task_id);
+    }
+
+    /// Get the compensation of a task to be revoked, whose entry should be locked.
+    fn get_compensation(&self, task_id: &K) -> Result<Compensation, Refusal<S>> {
+        self.compensations.read(task_id, |_, v| v.clone()).ok_or(Refusal::NoCompensation)
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::{HashMap, HashSet};
+use std::future::{poll_fn, Future};
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Poll;
+use crate::*;
+use super::is_in_progress;
+
+/// Resolves to whether all the prerequisites of a task succeed.
+pub(crate) type DependencyWait = Pin<Box<dyn Future<Output=bool> + Send + 'static>>;
+
+/// The prerequisites of the tasks waiting for them.
+pub(crate) type DependencyGraph<K> = Arc<Mutex<HashMap<K, Vec<K>>>>;
+
+/// Remove a task from the dependency graph when dropped,
+/// i.e. when it stops waiting for its prerequisites.
+pub(crate) struct DependencyRegistration<K>
+    where K: Eq + Hash {
+    graph: DependencyGraph<K>,
+    task_id: K,
+}
+
+impl<K> Drop for DependencyRegistration<K>
+    where K: Eq + Hash {
+    fn drop(&mut self) {
+        lock_graph(&self.graph).remove(&self.task_id);
+    }
+}
+
+fn lock_graph<K>(graph: &DependencyGraph<K>) -> MutexGuard<'_, HashMap<K, Vec<K>>> {
+    graph.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Dependency interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Record that the task is waiting for `prerequisites`.
+    ///
+    /// Return `None` if it would make a cycle.
+    pub(crate) fn register_dependencies(&self, task_id: &K, prerequisites: &[K]) -> Option<DependencyRegistration<K>> {
+        let mut graph = lock_graph(&self.dependencies);
+
+        // search from the prerequisites through the waiting tasks
+        let mut visited = HashSet::new();
+        let mut stack: Vec<&K> = prerequisites.iter().collect();
+        while let Some(current) = stack.pop() {
+            if current == task_id {
+                return None;
+            }
+            if visited.insert(current) {
+                if let Some(next) = graph.get(current) {
+                    stack.extend(next.iter());
+                }
+            }
+        }
+
+        graph.insert(task_id.clone(), prerequisites.to_vec());
+        Some(DependencyRegistration {
+            graph: self.dependencies.clone(),
+            task_id: task_id.clone(),
+        })
+    }
+
+    /// Create the `Future` waiting for `prerequisites`, which holds the registration until it finishes.
+    pub(crate) fn wait_dependencies(&self, registration: DependencyRegistration<K>, prerequisites: Vec<K>) -> DependencyWait {
+        // the ones already `Success` are not waited for,
+        // so that they are regarded as removed instead of not launched if they are removed before polling
+        let pending: Vec<K> = prerequisites.iter()
+            .filter(|prerequisite| self.recorder.read(*prerequisite, |_, v| *v == S::success()) != Some(true))
+            .cloned()
+            .collect();
+        let recorder = self.clone();
+        Box::pin(async move {
+            let _registration = registration;
+            // watch all of them at once, so that any failure is seen without waiting for the others
+            let mut waits: Vec<_> = pending.into_iter()
+                .map(|prerequisite| {
+                    let recorder = recorder.clone();
+                    Some(Box::pin(async move {
+                        // a revoking prerequisite has failed, and so has one in the failed state of the `TransitionTable`
+                        let settled = |state: &S| {
+                            *state == S::success() || *state == S::failed() || *state == S::revoking()
+                                || (!is_in_progress(state) && recorder.failures.contains(&prerequisite))
+                        };
+                        recorder.wait_for_state_matching(&prerequisite, settled).await == S::success()
+                    }))
+                })
+                .collect();
+            let succeeded = poll_fn(|cx| {
+                for wait in &mut waits {
+                    let Some(fut) = wait else {
+                        continue;
+                    };
+                    match fut.as_mut().poll(cx) {
+                        Poll::Ready(true) => *wait = None,
+                        Poll::Ready(false) => return Poll::Ready(false),
+                        Poll::Pending => {}
+                    }
+                }
+                match waits.iter().all(Option::is_none) {
+                    true => Poll::Ready(true),
+                    false => Poll::Pending,
+                }
+            }).await;
+            if !succeeded {
+                return false;
+            }
+            // some may be removed while waiting for the others
+            for prerequisite in &prerequisites {
+                if recorder.query_task_state(prerequisite).await != S::success() {
+                    return false;
+                }
+            }
+            true
+        })
+    }
+}
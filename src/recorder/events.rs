@@ -0,0 +1,202 @@
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::Stream;
+use crate::*;
+
+/// The default number of events kept for the subscribers which fall behind.
+pub(crate) const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// Why the state of a task changed.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum TransitionCause {
+    /// The task is launched. `NotFound` or `Failed` -> `Working` or `Queued`.
+    Launched,
+    /// The task gets a slot. `Queued` -> `Working`.
+    Started,
+    /// The task succeeded. `Working` -> `Success`.
+    Succeeded,
+    /// The task failed. `Working` or `Queued` -> `Failed`.
+    Failed(FailureReason),
+    /// The revoking is started. `Success` (or `Working` and `Queued` when taken over) -> `Revoking`.
+    RevokeStarted,
+    /// The revoking succeeded, or the task taken over failed. `Revoking` -> `NotFound`.
+    Revoked,
+    /// The revoking failed, panicked, timed out or was dropped.
+    /// `Revoking` -> `Success`, or the state decided by the [`RevokeFailurePolicy`](crate::RevokeFailurePolicy).
+    RevokeFailed,
+    /// The task expired. `Success` or `Failed` -> `NotFound`.
+    Expired,
+    /// The task is moved by [`AsyncTasksRecorder::transit`] following the [`TransitionTable`](crate::TransitionTable).
+    Transited,
+    /// The state is modified forcefully, e.g. by [`AsyncTasksRecorder::modify_state_force`].
+    Forced,
+}
+
+/// A change of a task's state.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct TransitionEvent<K, S = TaskState> {
+    pub task_id: K,
+    pub old_state: S,
+    pub new_state: S,
+    pub cause: TransitionCause,
+    /// When the state changed.
+    pub timestamp: SystemTime,
+}
+
+/// The subscriber fell behind, and the oldest events it had not received were dropped.
+///
+/// The number of the dropped events is included.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct EventsLagged(pub u64);
+
+/// Select the events received by a subscriber. All events are selected by default.
+#[derive(Debug, Clone)]
+pub struct TransitionFilter<K, S = TaskState> {
+    task_id: Option<K>,
+    new_states: Option<Vec<S>>,
+}
+
+impl<K, S> Default for TransitionFilter<K, S> {
+    fn default() -> Self {
+        TransitionFilter {
+            task_id: None,
+            new_states: None,
+        }
+    }
+}
+
+impl<K, S> TransitionFilter<K, S>
+    where K: Eq,
+          S: RecordedState {
+    /// Create to select all events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only select the events of the target task.
+    pub fn with_task_id(mut self, task_id: K) -> Self {
+        self.task_id = Some(task_id);
+        self
+    }
+
+    /// Only select the events whose new state is in `new_states`.
+    pub fn with_new_states(mut self, new_states: &[S]) -> Self {
+        self.new_states = Some(new_states.to_vec());
+        self
+    }
+
+    /// Whether `event` is selected.
+    pub fn matches(&self, event: &TransitionEvent<K, S>) -> bool {
+        if matches!(&self.task_id, Some(task_id) if *task_id != event.task_id) {
+            return false;
+        }
+        if matches!(&self.new_states, Some(states) if !states.contains(&event.new_state)) {
+            return false;
+        }
+        true
+    }
+}
+
+/// A [`Stream`] of the events of state transitions, created by [`AsyncTasksRecorder::subscribe_transitions`].
+///
+/// The events of a task are received in the order of its changes,
+/// because each of them is sent while the task's entry in the map is locked,
+/// the same as the [`TransitionObserver`](crate::TransitionObserver)s are called.
+/// The events of different tasks may be interleaved.
+///
+/// Each subscriber has its own position in a bounded buffer shared by all subscribers.
+/// When a subscriber falls behind by more than the capacity of the buffer,
+/// the oldest events are dropped for it, and it receives an `Err(EventsLagged)` before the next event.
+/// Senders are never blocked by the subscribers.
+///
+/// The stream ends when all the recorders sharing the buffer are dropped.
+pub struct TransitionStream<K, S = TaskState> {
+    inner: BroadcastStream<TransitionEvent<K, S>>,
+    filter: TransitionFilter<K, S>,
+}
+
+// nothing is pinned structurally
+impl<K, S> Unpin for TransitionStream<K, S> {}
+
+impl<K, S> Stream for TransitionStream<K, S>
+    where K: Eq + Clone + Send + 'static,
+          S: RecordedState {
+    type Item = Result<TransitionEvent<K, S>, EventsLagged>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let item = match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => {
+                    if !self.filter.matches(&event) {
+                        continue;
+                    }
+                    Ok(event)
+                }
+                Poll::Ready(Some(Err(BroadcastStreamRecvError::Lagged(n)))) => Err(EventsLagged(n)),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            return Poll::Ready(Some(item));
+        }
+    }
+}
+
+/// Event interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Set the number of events kept for the subscribers which fall behind.
+    /// It is 1024 by default.
+    ///
+    /// The existing subscribers would receive nothing more.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_event_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "event capacity should be positive");
+        self.events = broadcast::channel(capacity).0;
+        self
+    }
+
+    /// Subscribe to the events of state transitions selected by `filter`.
+    ///
+    /// Only the events after subscribing are received.
+    /// See [`TransitionStream`] for what happens when the subscriber falls behind.
+    pub fn subscribe_transitions(&self, filter: TransitionFilter<K, S>) -> TransitionStream<K, S> {
+        TransitionStream {
+            inner: BroadcastStream::new(self.events.subscribe()),
+            filter,
+        }
+    }
+
+    /// Whether anyone would receive the events.
+    ///
+    /// Check it before getting the `task_id` only for the event.
+    pub(crate) fn has_subscribers(&self) -> bool {
+        self.events.receiver_count() > 0
+    }
+
+    /// Send an event of state transition to the subscribers, and append the new state to the journal.
+    ///
+    /// Should be called while the entry of the task is locked,
+    /// so that the events and the records of a task are in order.
+    pub(crate) fn emit_transition(&self, task_id: &K, old_state: S, new_state: S, cause: TransitionCause) {
+        self.append_journal(task_id, &new_state);
+        if !self.has_subscribers() {
+            return;
+        }
+        let _ = self.events.send(TransitionEvent {
+            task_id: task_id.clone(),
+            old_state,
+            new_state,
+            cause,
+            timestamp: SystemTime::now(),
+        });
+    }
+}
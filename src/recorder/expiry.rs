@@ -0,0 +1,229 @@
+use std::borrow::Borrow;
+use std::future::{poll_fn, Future};
+use std::hash::Hash;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::Notify;
+use crate::*;
+
+/// Called with the `task_id` of an expired task while its entry is locked,
+/// to remove what is kept for it outside the recorder.
+pub(crate) type ExpireCleanup<K> = Arc<dyn Fn(&K) + Send + Sync + 'static>;
+
+/// Called with the `task_id` and the state of an expired task when it is removed.
+pub(crate) type ExpireHook<K, S> = Arc<dyn Fn(&K, S) + Send + Sync + 'static>;
+
+/// The handle of the sweeper spawned by [`spawn_sweeper`](AsyncTasksRecorder::spawn_sweeper).
+#[derive(Debug)]
+pub struct SweeperHandle {
+    stop: Arc<Notify>,
+}
+
+impl SweeperHandle {
+    /// Stop the sweeper. A sweeping in progress is finished first.
+    pub fn abort(&self) {
+        self.stop.notify_one();
+    }
+}
+
+/// Time-to-live of the finished tasks.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TtlConfig {
+    success: Option<Duration>,
+    failed: Option<Duration>,
+}
+
+impl TtlConfig {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.success.is_some() || self.failed.is_some()
+    }
+
+    /// Get the TTL of the tasks in the `state`.
+    fn get<S>(&self, state: &S) -> Option<Duration>
+        where S: RecordedState {
+        if *state == S::success() {
+            self.success
+        } else if *state == S::failed() {
+            self.failed
+        } else {
+            None
+        }
+    }
+}
+
+/// Expiry interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Make the `Success` tasks expire after `ttl` since they became `Success`.
+    ///
+    /// An expired task is regarded as `NotFound`. It is removed from the map
+    /// when it is queried or launched again, or by [`sweep_expired`](Self::sweep_expired).
+    ///
+    /// A task revoked unsuccessfully becomes `Success` again by default (see [`RevokeFailurePolicy`]), and its TTL restarts.
+    pub fn with_success_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl.success = Some(ttl);
+        self
+    }
+
+    /// Make the `Failed` tasks expire after `ttl` since they became `Failed`.
+    ///
+    /// See [`with_success_ttl`](Self::with_success_ttl).
+    pub fn with_failed_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl.failed = Some(ttl);
+        self
+    }
+
+    /// Set a hook called with the `task_id` and the state when an expired task is removed.
+    ///
+    /// A task removed by revoking or [`modify_state_force`](Self::modify_state_force) would not trigger this hook,
+    /// which tells expiry apart from them.
+    pub fn with_expire_hook<F>(mut self, hook: F) -> Self
+        where F: Fn(&K, S) + Send + Sync + 'static {
+        self.expire_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Remove all the expired tasks, and return their `task_id`s and states.
+    ///
+    /// The finished tasks which existed before TTL was recorded
+    /// (e.g. created by [`new_with_task_manager`](Self::new_with_task_manager))
+    /// are regarded as just finished.
+    pub async fn sweep_expired(&self) -> Vec<(K, S)> {
+        if !self.ttl.is_enabled() {
+            return Vec::new();
+        }
+
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        self.recorder.retain_async(|k, v| {
+            let Some(ttl) = self.ttl.get(v) else {
+                return true;
+            };
+            let finished_at = *self.finished_at.entry(k.clone()).or_insert(now).get();
+            if now.saturating_duration_since(finished_at) < ttl {
+                return true;
+            }
+            self.emit_expired(k, v.clone());
+            expired.push((k.clone(), v.clone()));
+            false
+        }).await;
+
+        for (task_id, state) in &expired {
+            self.on_entry_removed(task_id);
+            self.on_expired(task_id, state.clone());
+        }
+        expired
+    }
+
+    /// Spawn a task by the [`Spawner`] to call [`sweep_expired`](Self::sweep_expired) every `interval`.
+    ///
+    /// The spawned task holds a clone of this recorder,
+    /// and never stops until it is aborted by the returned handle (dropping the handle does not stop it).
+    pub fn spawn_sweeper(&self, interval: Duration) -> SweeperHandle {
+        let stop = Arc::new(Notify::new());
+        let recorder = self.clone();
+        let stop_cloned = stop.clone();
+        self.spawn(async move {
+            let mut stopped = pin!(stop_cloned.notified());
+            loop {
+                recorder.sweep_expired().await;
+                let mut sleep = pin!(recorder.sleep_until_deadline(Instant::now().checked_add(interval)));
+                let stopped = poll_fn(|cx| {
+                    if stopped.as_mut().poll(cx).is_ready() {
+                        return Poll::Ready(true);
+                    }
+                    sleep.as_mut().poll(cx).map(|_| false)
+                }).await;
+                if stopped {
+                    return;
+                }
+            }
+        });
+        SweeperHandle {
+            stop,
+        }
+    }
+
+    /// Whether the target task in `state` has expired.
+    pub(crate) fn is_expired<Q>(&self, task_id: &Q, state: &S) -> bool
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let Some(ttl) = self.ttl.get(state) else {
+            return false;
+        };
+        self.finished_at.read(task_id, |_, finished_at| finished_at.elapsed() >= ttl)
+            .unwrap_or(false)
+    }
+
+    /// Remove the target task if it has expired. Return whether it is removed.
+    pub(crate) async fn remove_if_expired<Q>(&self, task_id: &Q) -> bool
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let Some(ent) = self.recorder.get_async(task_id).await else {
+            return false;
+        };
+        if !self.is_expired(task_id, ent.get()) {
+            return false;
+        }
+        let state = ent.get().clone();
+        self.emit_expired(ent.key(), state.clone());
+        let (task_id, _) = ent.remove_entry();
+
+        self.on_entry_removed(&task_id);
+        self.on_expired(&task_id, state);
+        true
+    }
+
+    /// Set the cleanup called for each expired task, see [`ExpireCleanup`].
+    pub(crate) fn with_expire_cleanup<F>(mut self, cleanup: F) -> Self
+        where F: Fn(&K) + Send + Sync + 'static {
+        self.expire_cleanup = Some(Arc::new(cleanup));
+        self
+    }
+
+    /// Send the event of an expired task, and call the cleanup.
+    ///
+    /// Should be called while the entry of the task is locked, right before it is removed or replaced.
+    pub(crate) fn emit_expired(&self, task_id: &K, state: S) {
+        if let Some(cleanup) = &self.expire_cleanup {
+            cleanup(task_id);
+        }
+        self.emit_transition(task_id, state, S::not_found(), TransitionCause::Expired);
+    }
+
+    /// Called after an expired task is removed or replaced, whose event has been sent.
+    pub(crate) fn on_expired(&self, task_id: &K, state: S) {
+        if self.recorder.read(task_id, |_, _| ()).is_none() {
+            self.clear_run_records(task_id);
+            self.discard_compensation(task_id);
+            self.on_state_changed(task_id);
+        }
+        if let Some(hook) = &self.expire_hook {
+            hook(task_id, state);
+        }
+    }
+
+    /// Record when the target task finished, or remove the record if it is not finished.
+    pub(crate) fn update_finished_at<Q>(&self, task_id: &Q)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let record = self.recorder.read(task_id, |k, v| {
+            (k.clone(), self.ttl.get(v).is_some())
+        });
+        match record {
+            Some((task_id, true)) => {
+                self.finished_at.upsert(task_id, Instant::now());
+            }
+            Some((task_id, false)) => {
+                self.finished_at.remove(&task_id);
+            }
+            None => {
+                self.finished_at.remove(task_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use crate::*;
+
+/// The limits of a group of tasks. Used by [`AsyncTasksRecorder::with_group_limits`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub struct GroupLimits {
+    /// The maximum number of the `Working` tasks in the group. Unlimited if `None`.
+    pub max_working: Option<usize>,
+    /// The maximum number of the tasks of the group recorded in the map, whatever their states are.
+    /// Unlimited if `None`.
+    ///
+    /// A launch which would insert a new task over it is always refused with [`Quota::Entries`].
+    pub max_entries: Option<usize>,
+    /// What to do when launching a task while `max_working` is reached. `Reject` by default.
+    pub when_busy: WhenBusy,
+}
+
+/// What to do when launching a task while its group has reached `max_working`.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum WhenBusy {
+    /// Refuse the launch with [`Quota::Working`].
+    #[default]
+    Reject,
+    /// Make the task `Queued` until a `Working` task of the group finishes.
+    Queue,
+}
+
+/// A group and its limits.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Group {
+    /// Unique in a recorder.
+    pub(crate) id: usize,
+    pub(crate) limits: GroupLimits,
+}
+
+/// Map a task to its group.
+pub(crate) trait Grouping<K>: Send + Sync {
+    fn group_of(&self, task_id: &K) -> Group;
+}
+
+/// Give each group an id when it is seen for the first time.
+struct GroupIndex<G, F, L> {
+    group_of: F,
+    limits_of: L,
+    groups: Mutex<HashMap<G, Group>>,
+}
+
+impl<K, G, F, L> Grouping<K> for GroupIndex<G, F, L>
+    where G: Eq + Hash + Send,
+          F: Fn(&K) -> G + Send + Sync,
+          L: Fn(&G) -> GroupLimits + Send + Sync {
+    fn group_of(&self, task_id: &K) -> Group {
+        let group = (self.group_of)(task_id);
+        let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+        let id = groups.len();
+        *groups.entry(group).or_insert_with_key(|group| Group {
+            id,
+            limits: (self.limits_of)(group),
+        })
+    }
+}
+
+/// Group limiting interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Divide the tasks into groups by `group_of`, and limit each group by `limits_of`.
+    ///
+    /// A launch over a limit is refused with [`Refusal::QuotaExceeded`],
+    /// or makes the task `Queued` (see [`WhenBusy`]).
+    /// The limits of a group are decided when the group is seen for the first time,
+    /// and a group is never forgotten, so the number of groups should be bounded.
+    ///
+    /// Works together with [`with_concurrency_limit`](Self::with_concurrency_limit).
+    /// If several `Queued` tasks have the same priority,
+    /// the one whose group has fewer `Working` tasks becomes `Working` first.
+    ///
+    /// The entries inserted or removed by `modify_state_force` and `modify_to_success_before_work`
+    /// are counted but never refused.
+    /// The map should not be modified in other ways (e.g. by [`get_recorder_ref`](Self::get_recorder_ref)),
+    /// otherwise the counting would be wrong.
+    pub fn with_group_limits<G, F, L>(mut self, group_of: F, limits_of: L) -> Self
+        where G: Eq + Hash + Send + 'static,
+              F: Fn(&K) -> G + Send + Sync + 'static,
+              L: Fn(&G) -> GroupLimits + Send + Sync + 'static {
+        self.grouping = Some(Arc::new(GroupIndex {
+            group_of,
+            limits_of,
+            groups: Mutex::new(HashMap::new()),
+        }));
+        let limit = self.admission.as_ref().map_or(usize::MAX, |admission| admission.limit);
+        self.rebuild_admission(limit);
+        self
+    }
+
+    /// Get the group of the target task if the tasks are grouped.
+    pub(crate) fn group_of(&self, task_id: &K) -> Option<Group> {
+        self.grouping.as_ref().map(|grouping| grouping.group_of(task_id))
+    }
+
+    /// Should be called after a task is inserted into the map without [`admit`](Self::admit).
+    pub(crate) fn on_entry_inserted(&self, task_id: &K) {
+        if let (Some(group), Some(admission)) = (self.group_of(task_id), &self.admission) {
+            admission.add_entry(&group);
+        }
+    }
+
+    /// Should be called after a task is removed from the map.
+    pub(crate) fn on_entry_removed(&self, task_id: &K) {
+        if let (Some(group), Some(admission)) = (self.group_of(task_id), &self.admission) {
+            admission.remove_entry(&group);
+        }
+    }
+}
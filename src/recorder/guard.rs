@@ -0,0 +1,93 @@
+use std::future::{ready, Future};
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use crate::*;
+
+/// The `Future` returned by a [`TransitionGuard`].
+pub type GuardFuture<'a> = Pin<Box<dyn Future<Output=Result<(), Rejection>> + Send + 'a>>;
+
+/// Predicates deciding whether a task can be launched or revoked. All launches and revokings are allowed by default.
+///
+/// A guard is checked after the state of the task allows the launch or the revoking,
+/// without locking the task's entry in the map, so it may take time and query the recorder.
+/// Only one launch or revoking of a task is checked at a time, and the others wait for it.
+/// The transition is done only if the state has not been changed meanwhile (e.g. forcefully),
+/// otherwise the state is checked again, and so are the guards if needed.
+/// When rejected, the state of the task is left unchanged,
+/// and the methods launching or revoking return [`Refusal::Rejected`].
+///
+/// The guards **must not** launch or revoke the task being checked, which would deadlock.
+pub trait TransitionGuard<K, S = TaskState>: Send + Sync + 'static {
+    /// Check whether the target task in `state` (`NotFound` or `Failed`) can be launched.
+    fn check_launch<'a>(&'a self, _task_id: &'a K, _state: &'a S) -> GuardFuture<'a> {
+        Box::pin(ready(Ok(())))
+    }
+
+    /// Check whether the target task, which is `Success`, can be revoked.
+    fn check_revoke<'a>(&'a self, _task_id: &'a K) -> GuardFuture<'a> {
+        Box::pin(ready(Ok(())))
+    }
+}
+
+/// Guard interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Register a guard. The guards are checked in the order of registration,
+    /// and the first rejection is returned.
+    ///
+    /// See [`TransitionGuard`] for when the guards are checked.
+    pub fn with_guard<G>(mut self, guard: G) -> Self
+        where G: TransitionGuard<K, S> {
+        Arc::make_mut(&mut self.guards).push(Arc::new(guard));
+        self
+    }
+
+    /// Wait until no other launch or revoking of the target task is being checked by the guards,
+    /// and keep the others waiting until the returned lock is dropped.
+    pub(crate) async fn lock_for_guards(&self, task_id: K) -> GuardLock<'_, K> {
+        let lock = self.guard_locks.entry_async(task_id.clone()).await
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .get().clone();
+        GuardLock {
+            locks: &self.guard_locks,
+            task_id,
+            guard: Some(lock.lock_owned().await),
+        }
+    }
+
+    /// Check whether the target task in `state` can be launched by all the guards.
+    pub(crate) async fn check_launch_guards(&self, task_id: &K, state: &S) -> Result<(), Refusal<S>> {
+        for guard in self.guards.iter() {
+            guard.check_launch(task_id, state).await.map_err(Refusal::Rejected)?;
+        }
+        Ok(())
+    }
+
+    /// Check whether the target task can be revoked by all the guards.
+    pub(crate) async fn check_revoke_guards(&self, task_id: &K) -> Result<(), Refusal<S>> {
+        for guard in self.guards.iter() {
+            guard.check_revoke(task_id).await.map_err(Refusal::Rejected)?;
+        }
+        Ok(())
+    }
+}
+
+/// Held while the guards of a task are checked, and remove the lock from the map when no one else is waiting.
+pub(crate) struct GuardLock<'a, K>
+    where K: Eq + Hash {
+    locks: &'a scc::HashMap<K, Arc<Mutex<()>>>,
+    task_id: K,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl<K> Drop for GuardLock<'_, K>
+    where K: Eq + Hash {
+    fn drop(&mut self) {
+        self.guard = None;
+        // only the map holds the lock
+        self.locks.remove_if(&self.task_id, |v| Arc::strong_count(v) <= 1);
+    }
+}
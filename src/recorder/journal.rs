@@ -0,0 +1,288 @@
+// the writer is only started by `open_journal`, which needs `serde`
+#![cfg_attr(not(feature = "serde"), allow(dead_code))]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hash;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use tokio::sync::oneshot;
+#[cfg(feature = "serde")]
+use std::io::Read;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use crate::*;
+
+/// Encode a record of a task and its state as a line.
+type Encoder<K, S> = fn(&K, &S) -> io::Result<Vec<u8>>;
+
+/// An append-only file recording the state of a task after each of its changes.
+///
+/// Each record is a line of JSON `[task_id, state]`, and a removed task is recorded as `NotFound`.
+///
+/// The file is only touched by a dedicated writer thread,
+/// so that no runtime thread is blocked by the I/O.
+/// The remaining records are flushed when dropped.
+pub(crate) struct Journal<K, S> {
+    /// Always `Some` until dropped.
+    commands: Option<mpsc::Sender<Command<K, S>>>,
+    writer: Option<std::thread::JoinHandle<()>>,
+}
+
+enum Command<K, S> {
+    Append(K, S),
+    Compact(oneshot::Sender<io::Result<()>>),
+    Sync(oneshot::Sender<io::Result<()>>),
+}
+
+/// Owned by the writer thread.
+struct JournalWriter<K, S> {
+    path: PathBuf,
+    file: BufWriter<File>,
+    encode: Encoder<K, S>,
+    /// The latest state of each task in the file, which is written by compaction.
+    tasks: HashMap<K, S>,
+    /// The first error occurred when appending, which is reported by [`Journal::sync`].
+    error: Option<io::Error>,
+}
+
+impl<K, S> Journal<K, S>
+    where K: Eq + Hash + Send + 'static,
+          S: RecordedState {
+    /// Compact the file at `path` with `tasks`, and start the writer thread appending to it.
+    fn start(path: &Path, tasks: HashMap<K, S>, encode: Encoder<K, S>) -> io::Result<Self> {
+        let mut writer = JournalWriter {
+            path: path.to_owned(),
+            file: BufWriter::new(open_append(path)?),
+            encode,
+            tasks,
+            error: None,
+        };
+        writer.compact()?;
+
+        let (commands, receiver) = mpsc::channel();
+        let writer = std::thread::Builder::new()
+            .name("journal-writer".to_string())
+            .spawn(move || writer.run(receiver))?;
+        Ok(Journal {
+            commands: Some(commands),
+            writer: Some(writer),
+        })
+    }
+
+    /// Append the new state of the target task.
+    ///
+    /// Should be called while the task's entry is locked,
+    /// so that the records of a task are in the same order as its changes.
+    pub(crate) fn append(&self, task_id: &K, state: &S)
+        where K: Clone {
+        let _ = self.send(Command::Append(task_id.clone(), state.clone()));
+    }
+
+    /// Replace the file with the latest records of all the tasks.
+    pub(crate) async fn compact(&self) -> io::Result<()> {
+        self.request(Command::Compact).await
+    }
+
+    /// Flush the appended records to the disk,
+    /// and report the first error occurred when appending since the last call.
+    pub(crate) async fn sync(&self) -> io::Result<()> {
+        self.request(Command::Sync).await
+    }
+
+    async fn request<F>(&self, command: F) -> io::Result<()>
+        where F: FnOnce(oneshot::Sender<io::Result<()>>) -> Command<K, S> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let stopped = || io::Error::new(io::ErrorKind::BrokenPipe, "journal writer stopped");
+        self.send(command(reply_tx)).map_err(|_| stopped())?;
+        reply_rx.await.map_err(|_| stopped())?
+    }
+
+    fn send(&self, command: Command<K, S>) -> Result<(), mpsc::SendError<Command<K, S>>> {
+        self.commands.as_ref().expect("sender taken before dropped").send(command)
+    }
+}
+
+impl<K, S> Drop for Journal<K, S> {
+    fn drop(&mut self) {
+        // stop the writer after it handles the remaining commands
+        drop(self.commands.take());
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl<K, S> JournalWriter<K, S>
+    where K: Eq + Hash,
+          S: RecordedState {
+    /// Handle the commands until all the senders are dropped. The file is flushed whenever idle.
+    fn run(mut self, commands: mpsc::Receiver<Command<K, S>>) {
+        while let Ok(command) = commands.recv() {
+            self.handle(command);
+            while let Ok(command) = commands.try_recv() {
+                self.handle(command);
+            }
+            if let Err(e) = self.file.flush() {
+                self.error.get_or_insert(e);
+            }
+        }
+    }
+
+    fn handle(&mut self, command: Command<K, S>) {
+        match command {
+            Command::Append(task_id, state) => {
+                let res = (self.encode)(&task_id, &state)
+                    .and_then(|line| self.file.write_all(&line));
+                if let Err(e) = res {
+                    self.error.get_or_insert(e);
+                }
+                if state == S::not_found() {
+                    self.tasks.remove(&task_id);
+                } else {
+                    self.tasks.insert(task_id, state);
+                }
+            }
+            Command::Compact(reply) => {
+                let _ = reply.send(self.compact());
+            }
+            Command::Sync(reply) => {
+                let res = match self.error.take() {
+                    Some(e) => Err(e),
+                    None => self.file.flush().and_then(|_| self.file.get_ref().sync_data()),
+                };
+                let _ = reply.send(res);
+            }
+        }
+    }
+
+    /// Replace the file with the records in `tasks`.
+    ///
+    /// The new file is written aside and then renamed to the journal,
+    /// so the journal is always complete even if the process crashes during compaction.
+    fn compact(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let mut tmp_name = self.path.file_name().unwrap_or_default().to_owned();
+        tmp_name.push(".compacting");
+        let tmp_path = self.path.with_file_name(tmp_name);
+
+        let mut tmp_file = BufWriter::new(File::create(&tmp_path)?);
+        for (task_id, state) in &self.tasks {
+            tmp_file.write_all(&(self.encode)(task_id, state)?)?;
+        }
+        tmp_file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)?;
+
+        self.file = BufWriter::new(open_append(&self.path)?);
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    File::options().create(true).append(true).open(path)
+}
+
+#[cfg(feature = "serde")]
+fn encode<K: Serialize, S: Serialize>(task_id: &K, state: &S) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(&(task_id, state))?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Journal interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Create by replaying the journal at `path`, and record every change of state to it afterwards.
+    ///
+    /// A new journal is created if `path` does not exist.
+    /// The `Working` and `Revoking` tasks are mapped by `options`,
+    /// because their `Future`s no longer exist.
+    ///
+    /// The last record would be ignored if it is torn (e.g. the process crashed while writing it).
+    /// Any other broken record makes this method fail.
+    /// The journal is compacted after replaying, see [`compact_journal`](Self::compact_journal).
+    ///
+    /// The records are appended to the file by a dedicated thread,
+    /// which flushes them when idle and when the last clone of the recorder is dropped,
+    /// see [`sync_journal`](Self::sync_journal).
+    #[cfg(feature = "serde")]
+    pub fn open_journal<P>(path: P, options: &RestoreOptions<S>) -> io::Result<Self>
+        where K: Serialize + DeserializeOwned,
+              S: Serialize + DeserializeOwned,
+              P: AsRef<Path> {
+        let path = path.as_ref();
+        let mut content = Vec::new();
+        match File::open(path) {
+            Ok(mut file) => {
+                file.read_to_end(&mut content)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut tasks = HashMap::new();
+        let mut lines = content.split(|b| *b == b'\n').filter(|line| !line.is_empty()).peekable();
+        while let Some(line) = lines.next() {
+            let (task_id, state) = match serde_json::from_slice::<(K, S)>(line) {
+                Ok(record) => record,
+                // torn
+                Err(_) if lines.peek().is_none() => break,
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+            if state == S::not_found() {
+                tasks.remove(&task_id);
+            } else {
+                tasks.insert(task_id, state);
+            }
+        }
+
+        let mut recorder = Self::restore(tasks, options);
+        // the restored states, e.g. `Working` mapped to `Failed`
+        let mut restored = HashMap::new();
+        recorder.recorder.scan(|k, v| {
+            restored.insert(k.clone(), v.clone());
+        });
+        recorder.journal = Some(Journal::start(path, restored, encode::<K, S>)?.into());
+
+        Ok(recorder)
+    }
+
+    /// Rewrite the journal with only the current state of each task.
+    ///
+    /// The journal is written by a dedicated thread, so this does not block the runtime,
+    /// and the changes of state are still appended after compaction.
+    /// Do nothing if there is no journal.
+    pub async fn compact_journal(&self) -> io::Result<()> {
+        match &self.journal {
+            Some(journal) => journal.compact().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Flush the journal to the disk.
+    ///
+    /// Return the first error occurred when appending to the journal since the last call.
+    /// Do nothing if there is no journal.
+    pub async fn sync_journal(&self) -> io::Result<()> {
+        match &self.journal {
+            Some(journal) => journal.sync().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Append the new state of the target task to the journal if there is one.
+    ///
+    /// Should be called while the task's entry is locked after each change of its state,
+    /// including its removal.
+    pub(crate) fn append_journal(&self, task_id: &K, state: &S) {
+        if let Some(journal) = &self.journal {
+            journal.append(task_id, state);
+        }
+    }
+}
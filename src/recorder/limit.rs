@@ -0,0 +1,372 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use crate::*;
+use super::dependency::DependencyWait;
+use super::group::Group;
+
+/// Limit the number of the `Working` tasks in total and in each group, and let the others wait by priority.
+#[derive(Debug)]
+pub(crate) struct Admission {
+    /// `usize::MAX` if only the groups are limited.
+    pub(crate) limit: usize,
+    /// The effective priority of a waiting task increases by 1 every `aging`.
+    pub(crate) aging: Option<Duration>,
+    state: Mutex<AdmissionState>,
+}
+
+#[derive(Debug, Default)]
+struct AdmissionState {
+    /// The number of the permits given out.
+    running: usize,
+    /// The `Queued` tasks waiting for permits, in the order they are launched.
+    queue: VecDeque<Waiter>,
+    /// The number of the waiters in `queue` which are [`prioritized`](Waiter::is_prioritized).
+    prioritized: usize,
+    /// The usage of each group by its id.
+    groups: HashMap<usize, GroupUsage>,
+}
+
+#[derive(Debug, Default)]
+struct GroupUsage {
+    /// The number of the permits given out to the group.
+    working: usize,
+    /// The number of the tasks of the group in the map.
+    entries: usize,
+}
+
+/// A `Queued` task.
+#[derive(Debug)]
+struct Waiter {
+    /// A closed sender means the task has left the queue.
+    tx: oneshot::Sender<Permit>,
+    priority: i32,
+    enqueued_at: Instant,
+    group: Option<Group>,
+}
+
+impl Waiter {
+    /// Whether the waiter may be taken before the earlier ones, i.e. it has a priority or a group.
+    ///
+    /// Aging alone never changes the order of the waiters with the same priority.
+    fn is_prioritized(&self) -> bool {
+        self.priority != 0 || self.group.is_some()
+    }
+
+    fn effective_priority(&self, now: Instant, aging: Option<Duration>) -> i64 {
+        let aged = match aging {
+            Some(aging) => now.saturating_duration_since(self.enqueued_at).as_nanos() / aging.as_nanos().max(1),
+            None => 0,
+        };
+        i64::from(self.priority).saturating_add(i64::try_from(aged).unwrap_or(i64::MAX))
+    }
+}
+
+impl AdmissionState {
+    fn usage(&mut self, group_id: usize) -> &mut GroupUsage {
+        self.groups.entry(group_id).or_default()
+    }
+
+    /// The number of the `Working` tasks in `group`, or `None` if the group is full.
+    fn working_if_available(&self, group: &Option<Group>) -> Option<usize> {
+        let Some(group) = group else {
+            return Some(0);
+        };
+        let working = self.groups.get(&group.id).map_or(0, |usage| usage.working);
+        match group.limits.max_working {
+            Some(max_working) if working >= max_working => None,
+            _ => Some(working),
+        }
+    }
+
+    /// Take the waiting task with the highest effective priority among the ones whose groups are not full.
+    /// If there is a tie, the one whose group has fewer `Working` tasks is taken,
+    /// and then the earliest one.
+    ///
+    /// The first one is taken in O(1) if none is [`prioritized`](Waiter::is_prioritized),
+    /// otherwise all of them are scanned.
+    fn pop_next(&mut self, aging: Option<Duration>) -> Option<Waiter> {
+        if self.prioritized == 0 {
+            while let Some(waiter) = self.queue.pop_front() {
+                if !waiter.tx.is_closed() {
+                    return Some(waiter);
+                }
+            }
+            return None;
+        }
+
+        let prioritized = &mut self.prioritized;
+        self.queue.retain(|waiter| {
+            let closed = waiter.tx.is_closed();
+            if closed && waiter.is_prioritized() {
+                *prioritized -= 1;
+            }
+            !closed
+        });
+        let now = Instant::now();
+        let mut best: Option<(usize, i64, usize)> = None;
+        for (i, waiter) in self.queue.iter().enumerate() {
+            let Some(working) = self.working_if_available(&waiter.group) else {
+                continue;
+            };
+            let priority = waiter.effective_priority(now, aging);
+            let better = best.is_none_or(|(_, best_priority, best_working)| {
+                priority > best_priority || (priority == best_priority && working < best_working)
+            });
+            if better {
+                best = Some((i, priority, working));
+            }
+        }
+        let waiter = self.queue.remove(best?.0)?;
+        if waiter.is_prioritized() {
+            self.prioritized -= 1;
+        }
+        Some(waiter)
+    }
+
+    fn push(&mut self, waiter: Waiter) {
+        if waiter.is_prioritized() {
+            self.prioritized += 1;
+        }
+        self.queue.push_back(waiter);
+    }
+
+    fn acquire(&mut self, group_id: Option<usize>) {
+        self.running += 1;
+        if let Some(group_id) = group_id {
+            self.usage(group_id).working += 1;
+        }
+    }
+
+    fn release(&mut self, group_id: Option<usize>) {
+        self.running -= 1;
+        if let Some(group_id) = group_id {
+            self.usage(group_id).working -= 1;
+        }
+    }
+}
+
+impl Admission {
+    fn new(limit: usize, aging: Option<Duration>) -> Self {
+        Admission {
+            limit,
+            aging,
+            state: Mutex::new(AdmissionState::default()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, AdmissionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Release the permit of `group_id`, and give it to the next task still in the queue.
+    fn release(self: &Arc<Self>, group_id: Option<usize>) {
+        let mut state = self.lock();
+        state.release(group_id);
+        while state.running < self.limit {
+            let Some(waiter) = state.pop_next(self.aging) else {
+                break;
+            };
+            let group_id = waiter.group.map(|group| group.id);
+            state.acquire(group_id);
+            let permit = Permit {
+                admission: Some(self.clone()),
+                group_id,
+            };
+            if let Err(mut permit) = waiter.tx.send(permit) {
+                // the lock is held, so the returned permit must not be released by dropping
+                permit.admission = None;
+                state.release(group_id);
+            }
+        }
+    }
+
+    /// Take a slot if both the recorder and the group are not full, otherwise join the queue.
+    fn acquire_or_queue(self: &Arc<Self>, state: &mut AdmissionState, priority: i32, group: Option<Group>) -> Slot {
+        let group_available = state.working_if_available(&group).is_some();
+        let group_id = group.map(|group| group.id);
+        if group_available && state.running < self.limit {
+            state.acquire(group_id);
+            return Slot::Acquired(Permit {
+                admission: Some(self.clone()),
+                group_id,
+            });
+        }
+        let (tx, rx) = oneshot::channel();
+        state.push(Waiter {
+            tx,
+            priority,
+            enqueued_at: Instant::now(),
+            group,
+        });
+        Slot::Queued(rx)
+    }
+
+    /// Record that a task of `group` is inserted into the map.
+    pub(crate) fn add_entry(&self, group: &Group) {
+        self.lock().usage(group.id).entries += 1;
+    }
+
+    /// Record that a task of `group` is removed from the map.
+    pub(crate) fn remove_entry(&self, group: &Group) {
+        let mut state = self.lock();
+        let usage = state.usage(group.id);
+        usage.entries = usage.entries.saturating_sub(1);
+    }
+}
+
+/// A slot for a `Working` task, which is released when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    admission: Option<Arc<Admission>>,
+    group_id: Option<usize>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(admission) = self.admission.take() {
+            admission.release(self.group_id);
+        }
+    }
+}
+
+/// The slot of a launched task.
+#[derive(Debug)]
+pub(crate) enum Slot {
+    /// There is no concurrency limit.
+    Unlimited,
+    /// The task is `Working`. The permit is only held to be released when dropped.
+    Acquired(#[allow(dead_code)] Permit),
+    /// The task is `Queued`, and would receive a permit when it is its turn.
+    Queued(oneshot::Receiver<Permit>),
+    /// The task is `Queued` for its prerequisites, and would be admitted when they succeed.
+    Deferred {
+        priority: i32,
+        group: Option<Group>,
+    },
+}
+
+/// Got when a task is marked as `Working` or `Queued`, and should be passed to `launch_task_fut`.
+pub(crate) struct LaunchTicket {
+    /// The id of the run, see [`Canceller`](super::Canceller).
+    pub(crate) run_id: u64,
+    pub(crate) cancel_rx: CancelReceiver,
+    pub(crate) slot: Slot,
+    /// Resolves to whether the prerequisites succeed. The slot is `Deferred` if there is one.
+    pub(crate) dependencies: Option<DependencyWait>,
+    /// When the task is timed out.
+    pub(crate) deadline: Option<Instant>,
+}
+
+/// Concurrency limiting interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Limit the number of the `Working` tasks to `limit`.
+    ///
+    /// The tasks launched when the limit is reached become `Queued`,
+    /// and become `Working` in the order they are launched when the running tasks finish,
+    /// unless they are launched with priorities (see [`LaunchOptions::with_priority`]).
+    /// A `Queued` task can be aborted by [`abort_task`](Self::abort_task),
+    /// and its deadline (if any) includes the time in the queue.
+    ///
+    /// A task launched with retry occupies one slot during all its attempts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is 0.
+    pub fn with_concurrency_limit(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "concurrency limit should be positive");
+        self.rebuild_admission(limit);
+        self
+    }
+
+    /// Increase the priority of a `Queued` task by 1 every `interval` it waits,
+    /// so that the tasks with low priorities would not starve.
+    ///
+    /// Makes no difference unless there is a concurrency limit or a group limit,
+    /// no matter whether they are set before or after this.
+    pub fn with_priority_aging(mut self, interval: Duration) -> Self {
+        self.priority_aging = Some(interval);
+        if let Some(admission) = &self.admission {
+            let limit = admission.limit;
+            self.rebuild_admission(limit);
+        }
+        self
+    }
+
+    /// Get the number of the `Queued` tasks waiting for a slot.
+    ///
+    /// Always 0 if there is neither a concurrency limit
+    /// nor a group limit with [`WhenBusy::Queue`] (see [`with_group_limits`](Self::with_group_limits)).
+    ///
+    /// The tasks waiting for their prerequisites (see [`LaunchOptions::with_prerequisites`]) are also `Queued`,
+    /// but they are not counted until the prerequisites succeed.
+    pub fn query_queue_len(&self) -> usize {
+        match &self.admission {
+            Some(admission) => admission.lock().queue.iter().filter(|waiter| !waiter.tx.is_closed()).count(),
+            None => 0,
+        }
+    }
+
+    /// Take a slot for a task of `group` being launched with `priority`.
+    ///
+    /// `new_entry` means the task is not in the map yet,
+    /// and it is regarded as inserted if `Ok` is returned.
+    ///
+    /// If `deferred`, only the entry is taken, and the slot should be taken by [`admit_deferred`](Self::admit_deferred) later.
+    pub(crate) fn admit(&self, priority: i32, group: Option<Group>, new_entry: bool, deferred: bool) -> Result<Slot, Quota> {
+        let deferred_slot = Slot::Deferred {
+            priority,
+            group,
+        };
+        let Some(admission) = &self.admission else {
+            return Ok(if deferred { deferred_slot } else { Slot::Unlimited });
+        };
+
+        let mut state = admission.lock();
+        let group_available = state.working_if_available(&group).is_some();
+        if let Some(group) = &group {
+            let usage = state.usage(group.id);
+            if new_entry && group.limits.max_entries.is_some_and(|max_entries| usage.entries >= max_entries) {
+                return Err(Quota::Entries);
+            }
+            if !deferred && !group_available && group.limits.when_busy == WhenBusy::Reject {
+                return Err(Quota::Working);
+            }
+            if new_entry {
+                usage.entries += 1;
+            }
+        }
+
+        if deferred {
+            return Ok(deferred_slot);
+        }
+        Ok(admission.acquire_or_queue(&mut state, priority, group))
+    }
+
+    /// Take a slot for a task whose prerequisites have succeeded.
+    ///
+    /// The task is always queued if its group is full, whatever `when_busy` is,
+    /// because it can no longer be refused.
+    pub(crate) fn admit_deferred(&self, priority: i32, group: Option<Group>) -> Slot {
+        match &self.admission {
+            Some(admission) => admission.acquire_or_queue(&mut admission.lock(), priority, group),
+            None => Slot::Unlimited,
+        }
+    }
+
+    /// Replace the admission with a new one, and count the entries of each group in the map.
+    ///
+    /// Only called when building, so the old one has nothing to move.
+    pub(crate) fn rebuild_admission(&mut self, limit: usize) {
+        let admission = Admission::new(limit, self.priority_aging);
+        if let Some(grouping) = &self.grouping {
+            let mut state = admission.lock();
+            self.recorder.scan(|k, _| state.usage(grouping.group_of(k).id).entries += 1);
+        }
+        self.admission = Some(admission.into());
+    }
+}
@@ -0,0 +1,67 @@
+use std::hash::Hash;
+use std::sync::Arc;
+use crate::*;
+
+/// Callbacks invoked inline when a task changes its state. All methods do nothing by default.
+///
+/// Each method is called while the task's entry in the map is locked, right after the state is updated
+/// (or right before the task is removed, for [`on_revoked`](Self::on_revoked)).
+/// Therefore, for the same task:
+///
+/// - The calls are in the same order as the transitions,
+///   and the next transition cannot happen until the current call returns.
+/// - The call happens before the waiters are woken up, the journal is appended
+///   and the [`TransitionEvent`] is sent.
+///
+/// The methods should be fast and should not panic.
+/// They **must not** call any method of the recorder, which would deadlock.
+///
+/// Forced modifications and expiry are not observed.
+pub trait TransitionObserver<K, S = TaskState>: Send + Sync + 'static {
+    /// The task becomes `Working` or `Queued` from `old_state` (`NotFound` or `Failed`).
+    fn on_launch(&self, _task_id: &K, _old_state: &S) {}
+
+    /// The task becomes `Working` from `Queued`.
+    fn on_start(&self, _task_id: &K) {}
+
+    /// The task becomes `Success` from `Working`.
+    fn on_success(&self, _task_id: &K) {}
+
+    /// The task becomes `Failed` from `Working`.
+    fn on_failure(&self, _task_id: &K, _reason: &FailureReason) {}
+
+    /// The task becomes `Revoking` from `Success`,
+    /// or from `Working` or `Queued` by [`AsyncTasksRecorder::revoke_task_even_working`].
+    fn on_revoke_start(&self, _task_id: &K) {}
+
+    /// The task is about to be removed because the revoking succeeded.
+    fn on_revoked(&self, _task_id: &K) {}
+
+    /// The task becomes `Success` (or the state decided by the [`RevokeFailurePolicy`]) from `Revoking`
+    /// because the revoking failed.
+    fn on_revoke_failed(&self, _task_id: &K) {}
+}
+
+/// Observer interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Register an observer. The observers are called in the order of registration.
+    ///
+    /// See [`TransitionObserver`] for when the methods are called.
+    pub fn with_observer<O>(mut self, observer: O) -> Self
+        where O: TransitionObserver<K, S> {
+        Arc::make_mut(&mut self.observers).push(Arc::new(observer));
+        self
+    }
+
+    /// Call `f` with each observer.
+    ///
+    /// Should be called while the entry of the task is locked.
+    pub(crate) fn observe<F>(&self, f: F)
+        where F: Fn(&dyn TransitionObserver<K, S>) {
+        for observer in self.observers.iter() {
+            f(observer.as_ref());
+        }
+    }
+}
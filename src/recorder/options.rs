@@ -0,0 +1,132 @@
+use std::future::Future;
+use std::hash::Hash;
+use std::panic::resume_unwind;
+use std::time::{Duration, Instant};
+use crate::*;
+
+/// How a task is launched, which can be passed to every method launching a task.
+///
+/// The default options launch a task like [`AsyncTasksRecorder::launch`].
+#[derive(Debug, Clone)]
+pub struct LaunchOptions<K> {
+    priority: i32,
+    timeout: Option<Duration>,
+    prerequisites: Vec<K>,
+}
+
+impl<K> Default for LaunchOptions<K> {
+    fn default() -> Self {
+        LaunchOptions {
+            priority: 0,
+            timeout: None,
+            prerequisites: Vec::new(),
+        }
+    }
+}
+
+impl<K> LaunchOptions<K> {
+    /// Create the default options, i.e. priority 0, no timeout and no prerequisites.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If the task becomes `Queued`, it becomes `Working` before the `Queued` tasks with lower priorities.
+    ///
+    /// The tasks with the same priority become `Working` in the order they are launched.
+    /// The priority is 0 by default.
+    ///
+    /// See [`with_priority_aging`](AsyncTasksRecorder::with_priority_aging) to prevent the tasks with low priorities from starving.
+    /// The priority makes no difference if there is no concurrency limit.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// The task would be dropped and become `Failed` if it does not finish within `timeout`,
+    /// including the time it is `Queued`.
+    /// The [`FailureReason`] of such task is `TimedOut`.
+    ///
+    /// A task launched with retry is timed out as a whole, including all its attempts.
+    /// There is no timeout if `timeout` is too large to represent.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The task is `Queued` until all the `prerequisites` are `Success`,
+    /// and it does not take a slot of the concurrency limit until then.
+    /// A `NotFound` prerequisite is waited for until it is launched and succeeds.
+    ///
+    /// The task becomes `Failed` with [`FailureReason::DependencyFailed`]
+    /// if any prerequisite becomes `Failed` (or the state decided by [`TransitionTable::failed_state`])
+    /// or `Revoking` before the task starts,
+    /// or is removed (e.g. revoked or expired) after it is seen as `Success` (including when launching).
+    /// The prerequisites are no longer watched after the task starts.
+    ///
+    /// The launch is refused with [`Refusal::DependencyCycle`] if the task depends on itself,
+    /// directly or through other tasks waiting for their prerequisites.
+    pub fn with_prerequisites<I>(mut self, prerequisites: I) -> Self
+        where I: IntoIterator<Item=K> {
+        self.prerequisites = prerequisites.into_iter().collect();
+        self
+    }
+
+    pub(crate) fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// The deadline of a task launched now.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.timeout.and_then(|timeout| Instant::now().checked_add(timeout))
+    }
+
+    pub(crate) fn take_prerequisites(&mut self) -> Vec<K> {
+        std::mem::take(&mut self.prerequisites)
+    }
+}
+
+/// Launching with options interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Launch a task with `options` and execute it asynchronously.
+    ///
+    /// Return **immediately**.
+    ///
+    /// Same as [`launch`](Self::launch), but the task is launched as `options` says.
+    pub async fn launch_with_options<Fut, R, E>(&self, task_id: K, task: Fut, options: LaunchOptions<K>) -> Result<(), (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+
+        // start
+        self.spawn_launched_task(task_id, task, ticket);
+
+        Ok(())
+    }
+
+    /// Launch a task with `options`.
+    ///
+    /// Same as [`launch_block`](Self::launch_block), but the task is launched as `options` says.
+    ///
+    /// Return `Ok(None)` if the task is aborted, timed out or any prerequisite fails.
+    pub async fn launch_block_with_options<Fut, R, E>(&self, task_id: K, task: Fut, options: LaunchOptions<K>) -> Result<Option<Result<R, E>>, (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+
+        // start (block)
+        match self.launch_task_fut(task_id, task, ticket, |_| ()).await {
+            Ok(res) => Ok(res),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::panic::resume_unwind;
+use std::sync::Arc;
+use crate::*;
+
+/// Called with the `task_id` and the payload when a task executed asynchronously panics.
+pub(crate) type PanicHook<K> = Arc<dyn Fn(&K, PanicPayload) + Send + Sync + 'static>;
+
+/// Panic handling interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Set a hook to receive the panics of the `Future`s executed asynchronously
+    /// (i.e. by [`launch`](Self::launch) and [`revoke_task`](Self::revoke_task)).
+    ///
+    /// No matter whether the hook is set,
+    /// a panicking task would become `Failed`, and a panicking revoking is handled like a failed one,
+    /// i.e. the task is restored or changed by the [`RevokeFailurePolicy`].
+    ///
+    /// If no hook is set, the panic would be resumed in the spawned `tokio` task.
+    /// The `*_block` methods always resume the panic to their callers.
+    pub fn with_panic_hook<F>(mut self, hook: F) -> Self
+        where F: Fn(&K, PanicPayload) + Send + Sync + 'static {
+        self.panic_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Pass the panic of a task executed asynchronously to the hook, or resume it.
+    pub(crate) fn handle_panic<Q>(&self, task_id: &Q, payload: PanicPayload)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let Some(hook) = &self.panic_hook else {
+            resume_unwind(payload);
+        };
+        // the hook requires `&K`
+        match self.recorder.read(task_id, |k, _| k.clone()) {
+            Some(task_id) => hook(&task_id, payload),
+            // has been removed by others
+            None => resume_unwind(payload),
+        }
+    }
+}
+
+/// Change the state of a task when dropped,
+/// in case the `Future` executing the task is dropped before it changes the state.
+///
+/// E.g. the `tokio` task is aborted or the runtime is shutting down.
+pub(crate) struct DropGuard<'a, K, S, Q>
+    where K: Eq + Hash + Clone + Send + Sync + Borrow<Q> + 'static,
+          S: RecordedState,
+          Q: Hash + Eq + ?Sized {
+    recorder: &'a AsyncTasksRecorder<K, S>,
+    task_id: &'a Q,
+    state_on_drop: S,
+    failure_on_drop: Option<FailureReason>,
+    /// Guard a revoking instead of a launched task.
+    revoking: bool,
+    armed: bool,
+}
+
+impl<'a, K, S, Q> DropGuard<'a, K, S, Q>
+    where K: Eq + Hash + Clone + Send + Sync + Borrow<Q> + 'static,
+          S: RecordedState,
+          Q: Hash + Eq + ?Sized {
+    pub(crate) fn new(
+        recorder: &'a AsyncTasksRecorder<K, S>,
+        task_id: &'a Q,
+        state_on_drop: S,
+        failure_on_drop: Option<FailureReason>)
+        -> Self {
+        DropGuard {
+            recorder,
+            task_id,
+            state_on_drop,
+            failure_on_drop,
+            revoking: false,
+            armed: true,
+        }
+    }
+
+    /// Guard a revoking. `failure_on_drop` is recorded only if it is `Some`.
+    pub(crate) fn revoking(
+        recorder: &'a AsyncTasksRecorder<K, S>,
+        task_id: &'a Q,
+        state_on_drop: S,
+        failure_on_drop: Option<FailureReason>)
+        -> Self {
+        let mut guard = Self::new(recorder, task_id, state_on_drop, failure_on_drop);
+        guard.revoking = true;
+        guard
+    }
+
+    /// Called after the state has been changed normally.
+    pub(crate) fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl<K, S, Q> Drop for DropGuard<'_, K, S, Q>
+    where K: Eq + Hash + Clone + Send + Sync + Borrow<Q> + 'static,
+          S: RecordedState,
+          Q: Hash + Eq + ?Sized {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        // a dropped `Working` or `Queued` task is no longer abortable
+        self.recorder.cancellers.remove(self.task_id);
+        self.recorder.clear_progress(self.task_id);
+        let failure = self.failure_on_drop.take();
+        let cause = match &failure {
+            Some(failure) if !self.revoking => TransitionCause::Failed(failure.clone()),
+            _ => TransitionCause::RevokeFailed,
+        };
+        let mut taken_over = false;
+        let task_id = self.recorder.recorder.update(self.task_id, |k, v| {
+            // a task taken over by a revoking is left to it
+            if !self.revoking && *v == S::revoking() {
+                taken_over = true;
+                return None;
+            }
+            let old_state = std::mem::replace(v, self.state_on_drop.clone());
+            match &cause {
+                TransitionCause::Failed(failure) => self.recorder.observe(|o| o.on_failure(k, failure)),
+                _ => self.recorder.observe(|o| o.on_revoke_failed(k)),
+            }
+            self.recorder.emit_transition(k, old_state, self.state_on_drop.clone(), cause);
+            Some(k.clone())
+        }).flatten();
+        if taken_over {
+            self.recorder.finish_taken_over(self.task_id, false);
+            return;
+        }
+        if let (Some(task_id), Some(failure)) = (&task_id, failure) {
+            self.recorder.failures.upsert(task_id.clone(), failure);
+        }
+        if let (Some(task_id), true) = (&task_id, self.revoking) {
+            self.recorder.revoke_errors.remove(task_id);
+        }
+        self.recorder.on_state_changed(self.task_id);
+    }
+}
@@ -0,0 +1,182 @@
+use std::any::Any;
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::hash::Hash;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Weak};
+use tokio::sync::watch;
+use crate::*;
+
+/// The progress of a `Working` task reported by [`ProgressReporter`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress {
+    /// The fraction done, usually between 0 and 1.
+    Fraction(f64),
+    /// The number of bytes done, and the total number of bytes if known.
+    Bytes {
+        done: u64,
+        total: Option<u64>,
+    },
+    /// Any other progress.
+    Custom(ProgressPayload),
+}
+
+/// A custom payload of [`Progress`], which can be shared.
+///
+/// Two payloads are equal only if they are the same one.
+#[derive(Clone)]
+pub struct ProgressPayload(Arc<dyn Any + Send + Sync>);
+
+impl ProgressPayload {
+    /// Wrap a custom payload.
+    pub fn new<T>(payload: T) -> Self
+        where T: Any + Send + Sync {
+        ProgressPayload(Arc::new(payload))
+    }
+
+    /// Get the payload if it is of type `T`.
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+        where T: Any {
+        self.0.downcast_ref()
+    }
+}
+
+impl Debug for ProgressPayload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ProgressPayload").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for ProgressPayload {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Passed to a task launched by [`AsyncTasksRecorder::launch_with_progress`] to report its progress.
+///
+/// Reporting does nothing after the task finishes.
+#[derive(Debug, Clone)]
+pub struct ProgressReporter {
+    sender: Weak<watch::Sender<Option<Progress>>>,
+}
+
+impl ProgressReporter {
+    /// Replace the progress of the task.
+    pub fn report(&self, progress: Progress) {
+        if let Some(sender) = self.sender.upgrade() {
+            sender.send_replace(Some(progress));
+        }
+    }
+}
+
+/// Progress interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Launch a task which reports its progress, and execute it asynchronously.
+    ///
+    /// Same as [`launch`](Self::launch), but the task is created by `task` with a [`ProgressReporter`].
+    /// The reported progress can be got by [`query_progress`](Self::query_progress)
+    /// and [`subscribe_progress`](Self::subscribe_progress), and is cleared when the task finishes.
+    /// The task is launched as `options` says, see [`LaunchOptions`].
+    ///
+    /// `task` is not called if the launch is refused, and is returned with `Err`.
+    /// If `task` panics, the task becomes `Failed` like a panicking task.
+    pub async fn launch_with_progress<F, Fut, R, E>(&self, task_id: K, task: F, options: LaunchOptions<K>) -> Result<(), (Refusal<S>, F)>
+        where F: FnOnce(ProgressReporter) -> Fut,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+        let task = self.create_with_progress(&task_id, task);
+
+        // start
+        self.spawn_launched_task(task_id, task, ticket);
+
+        Ok(())
+    }
+
+    /// Launch a task which reports its progress.
+    ///
+    /// Same as [`launch_block`](Self::launch_block),
+    /// but the task is created by `task` with a [`ProgressReporter`].
+    /// See [`launch_with_progress`](Self::launch_with_progress).
+    pub async fn launch_block_with_progress<F, Fut, R, E>(&self, task_id: K, task: F, options: LaunchOptions<K>) -> Result<Option<Result<R, E>>, (Refusal<S>, F)>
+        where F: FnOnce(ProgressReporter) -> Fut,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+        let task = self.create_with_progress(&task_id, task);
+
+        // start (block)
+        match self.launch_task_fut(task_id, task, ticket, |_| ()).await {
+            Ok(res) => Ok(res),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+
+    /// Query the latest progress reported by the target task.
+    ///
+    /// Return `None` if the task has not reported, has finished,
+    /// or is not launched by [`launch_with_progress`](Self::launch_with_progress).
+    pub fn query_progress<Q>(&self, task_id: &Q) -> Option<Progress>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.progress.read(task_id, |_, sender| watch::Sender::borrow(sender).clone()).flatten()
+    }
+
+    /// Watch the progress reported by the target task.
+    ///
+    /// The progress becomes `None` when the task finishes, and then the channel is closed.
+    /// Return `None` if the task has finished or is not launched by [`launch_with_progress`](Self::launch_with_progress).
+    pub fn subscribe_progress<Q>(&self, task_id: &Q) -> Option<watch::Receiver<Option<Progress>>>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.progress.read(task_id, |_, sender| sender.subscribe())
+    }
+
+    /// Create the task by `task` with its reporter.
+    ///
+    /// A panic of `task` is resumed when the created `Future` is polled,
+    /// so that the task fails like any other panicking task instead of being left `Working`.
+    fn create_with_progress<F, Fut, R, E>(&self, task_id: &K, task: F) -> impl Future<Output=Result<R, E>> + Send + 'static
+        where F: FnOnce(ProgressReporter) -> Fut,
+              Fut: Future<Output=Result<R, E>> + Send + 'static {
+        let reporter = self.register_progress(task_id);
+        let created = catch_unwind(AssertUnwindSafe(|| task(reporter)));
+        async move {
+            match created {
+                Ok(task) => task.await,
+                Err(payload) => resume_unwind(payload),
+            }
+        }
+    }
+
+    /// Create the progress of the target task, and return its reporter.
+    fn register_progress(&self, task_id: &K) -> ProgressReporter {
+        let sender = Arc::new(watch::Sender::new(None));
+        let reporter = ProgressReporter {
+            sender: Arc::downgrade(&sender),
+        };
+        self.progress.upsert(task_id.clone(), sender);
+        reporter
+    }
+
+    /// Clear the progress of the target task, which is finishing.
+    pub(crate) fn clear_progress<Q>(&self, task_id: &Q)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        if let Some((_, sender)) = self.progress.remove(task_id) {
+            sender.send_replace(None);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::panic::resume_unwind;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::*;
+
+/// The delay between two attempts of a task.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Backoff {
+    /// Retry immediately.
+    None,
+    /// Wait for the same time before each retry.
+    Fixed(Duration),
+    /// Wait for `initial * factor ^ (n - 1)` before the `n`th retry, but no longer than `max`.
+    Exponential {
+        initial: Duration,
+        factor: u32,
+        max: Duration,
+    },
+}
+
+/// Decide whether and when a failed task should be retried.
+///
+/// Used by [`AsyncTasksRecorder::launch_with_retry`].
+///
+/// Only the tasks returning `Err` are retried. A panicking task is never retried.
+pub struct RetryPolicy<E> {
+    max_attempts: usize,
+    backoff: Backoff,
+    jitter: bool,
+    retry_if: Option<RetryIf<E>>,
+}
+
+/// Decide whether to retry on an `Err`.
+type RetryIf<E> = Arc<dyn Fn(&E) -> bool + Send + Sync + 'static>;
+
+impl<E> RetryPolicy<E> {
+    /// Create a policy which executes a task at most `max_attempts` times (including the first one),
+    /// retries immediately, and retries on every `Err`.
+    ///
+    /// `max_attempts` is regarded as `1` if it is `0`.
+    pub fn new(max_attempts: usize) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            backoff: Backoff::None,
+            jitter: false,
+            retry_if: None,
+        }
+    }
+
+    /// Set the delay between two attempts.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Randomize each delay to a value between a half of it and itself,
+    /// so that the tasks failed together would not be retried together.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Only retry when `retry_if` returns `true` for the `Err`.
+    pub fn with_retry_if<F>(mut self, retry_if: F) -> Self
+        where F: Fn(&E) -> bool + Send + Sync + 'static {
+        self.retry_if = Some(Arc::new(retry_if));
+        self
+    }
+
+    /// Get the maximum number of attempts.
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// Whether to retry after the `attempt`th attempt (starting from `1`) returns `err`.
+    pub fn should_retry(&self, attempt: usize, err: &E) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        match &self.retry_if {
+            Some(retry_if) => retry_if(err),
+            None => true,
+        }
+    }
+
+    /// Get the delay before the `retry`th retry (starting from `1`).
+    pub fn delay(&self, retry: usize) -> Duration {
+        let delay = match &self.backoff {
+            Backoff::None => return Duration::ZERO,
+            Backoff::Fixed(delay) => *delay,
+            Backoff::Exponential { initial, factor, max } => {
+                let exp = u32::try_from(retry.saturating_sub(1)).unwrap_or(u32::MAX);
+                factor.checked_pow(exp)
+                    .and_then(|mul| initial.checked_mul(mul))
+                    .map_or(*max, |delay| delay.min(*max))
+            }
+        };
+
+        if self.jitter {
+            let half = delay / 2;
+            half + half.mul_f64(fastrand::f64())
+        } else {
+            delay
+        }
+    }
+}
+
+impl<E> Clone for RetryPolicy<E> {
+    fn clone(&self) -> Self {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            backoff: self.backoff.clone(),
+            jitter: self.jitter,
+            retry_if: self.retry_if.clone(),
+        }
+    }
+}
+
+impl<E> std::fmt::Debug for RetryPolicy<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("jitter", &self.jitter)
+            .field("retry_if", &self.retry_if.is_some())
+            .finish()
+    }
+}
+
+/// Retrying interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Launch a task which would be retried on failure, and execute it asynchronously.
+    ///
+    /// Return **immediately**.
+    ///
+    /// `task_factory` is called to create a new `Future` for each attempt.
+    /// The task keeps `Working` between attempts,
+    /// and only becomes `Failed` when the last attempt allowed by `policy` fails.
+    /// The task is launched as `options` says, see [`LaunchOptions`].
+    ///
+    /// Can only launch successfully when the target task is `NotFound` or `Failed`.
+    /// Return `Err` when the state does not meet the requirements, a guard rejects, or a quota of its group is exceeded.
+    /// `Err` would include the task's current state, the rejection of the [`TransitionGuard`], or the exceeded [`Quota`].
+    pub async fn launch_with_retry<F, Fut, R, E>(&self, task_id: K, task_factory: F, policy: RetryPolicy<E>, options: LaunchOptions<K>) -> Result<(), (Refusal<S>, F)>
+        where F: FnMut() -> Fut + Send + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send + 'static,
+              E: Send + 'static {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task_factory)),
+        };
+
+        // start
+        let task = self.clone().retry_task_fut(task_id.clone(), task_factory, policy);
+        self.spawn_launched_task(task_id, task, ticket);
+
+        Ok(())
+    }
+
+    /// Launch a task which would be retried on failure.
+    ///
+    /// Not return (keep awaiting) until the last attempt finishes when successfully launch.
+    ///
+    /// See [`launch_with_retry`](Self::launch_with_retry).
+    ///
+    /// Return `Ok(None)` if the task is aborted, timed out or any prerequisite fails.
+    pub async fn launch_block_with_retry<F, Fut, R, E>(&self, task_id: K, task_factory: F, policy: RetryPolicy<E>, options: LaunchOptions<K>) -> Result<Option<Result<R, E>>, (Refusal<S>, F)>
+        where F: FnMut() -> Fut + Send + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send + 'static,
+              E: Send + 'static {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task_factory)),
+        };
+
+        // start (block)
+        let task = self.clone().retry_task_fut(task_id.clone(), task_factory, policy);
+        match self.launch_task_fut(task_id, task, ticket, |_| ()).await {
+            Ok(res) => Ok(res),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+
+    /// Query how many times the target task has been attempted.
+    ///
+    /// Return `None` if the task is not launched by [`launch_with_retry`](Self::launch_with_retry)
+    /// or [`launch_block_with_retry`](Self::launch_block_with_retry).
+    ///
+    /// When the task is `Working`, the current attempt is included.
+    pub async fn query_task_attempts<Q>(&self, task_id: &Q) -> Option<usize>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.attempts.read_async(task_id, |_, v| *v).await
+    }
+
+    /// The async function to execute the attempts of a task until success or the `policy` is exhausted.
+    async fn retry_task_fut<F, Fut, R, E>(self, task_id: K, mut task_factory: F, policy: RetryPolicy<E>) -> Result<R, E>
+        where F: FnMut() -> Fut + Send + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send + 'static,
+              E: Send + 'static {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.attempts.upsert_async(task_id.clone(), attempt).await;
+
+            match task_factory().await {
+                Err(err) if policy.should_retry(attempt, &err) => {
+                    // a delay too large to represent is never over
+                    self.sleep_until_deadline(Instant::now().checked_add(policy.delay(attempt))).await;
+                }
+                res => return res,
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use crate::*;
+
+/// The output of a `Future` for revoking.
+pub(crate) trait RevokeOutput {
+    type Ok;
+    type Err;
+
+    fn into_result(self) -> Result<Self::Ok, Self::Err>;
+}
+
+impl<R, E> RevokeOutput for Result<R, E> {
+    type Ok = R;
+    type Err = E;
+
+    fn into_result(self) -> Result<R, E> {
+        self
+    }
+}
+
+/// Wrap the error in the output of `Fut`.
+///
+/// The error is only `'static` by being the output of a `'static` `Future`,
+/// so that the callers are not required to bound it.
+pub(crate) fn wrap_revoke_error<Fut>(output: Fut::Output) -> Result<<Fut::Output as RevokeOutput>::Ok, RevokeError>
+    where Fut: Future + 'static,
+          Fut::Output: RevokeOutput,
+          <Fut::Output as RevokeOutput>::Err: Send {
+    output.into_result().map_err(RevokeError::new)
+}
+
+/// What the task becomes when its revoking fails, panics, is timed out or dropped.
+///
+/// Set by [`AsyncTasksRecorder::with_revoke_failure_policy`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum RevokeFailurePolicy {
+    /// Back to the state before revoking, usually `Success`.
+    #[default]
+    Restore,
+    /// Become `RevokeFailed`, which can be revoked again
+    /// or moved back to `Success` by [`modify_to_success_after_revoke_failed`](AsyncTasksRecorder::modify_to_success_after_revoke_failed).
+    RevokeFailed,
+    /// Become `Failed`, which can be launched again.
+    Failed,
+}
+
+/// Revoking failure interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Set what the task becomes when its revoking fails. [`RevokeFailurePolicy::Restore`] by default.
+    ///
+    /// Unless restored, the reason why the revoking failed can be got by [`query_failure_reason`](Self::query_failure_reason).
+    /// The error itself is returned by the `*_block` methods of revoking,
+    /// or recorded for [`query_revoke_error`](Self::query_revoke_error) otherwise.
+    pub fn with_revoke_failure_policy(mut self, policy: RevokeFailurePolicy) -> Self {
+        self.revoke_failure_policy = policy;
+        self
+    }
+
+    /// Query the error of the last revoking of the target task, if it was executed asynchronously and returned `Err`.
+    ///
+    /// The error is kept no matter what the task becomes by the [`RevokeFailurePolicy`],
+    /// until the task is revoked again, launched again or removed.
+    /// The `*_block` methods of revoking return the error instead of recording it.
+    pub async fn query_revoke_error<Q>(&self, task_id: &Q) -> Option<RevokeError>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.revoke_errors.read_async(task_id, |_, v| v.clone()).await
+    }
+
+    /// Change task's state to `Success` atomically when task is `RevokeFailed`,
+    /// e.g. the revoking is found to have done nothing.
+    ///
+    /// - Return `Ok(())` if succeed.
+    /// - Return `Err(task_state)` if failed and the task was in `task_state` state.
+    pub async fn modify_to_success_after_revoke_failed(&self, target_task_id: K) -> Result<(), S> {
+        let Some(mut ent) = self.recorder.get_async(&target_task_id).await else {
+            return Err(S::not_found());
+        };
+        if *ent.get() != S::revoke_failed() {
+            return Err(ent.get().clone());
+        }
+        self.clear_run_records(&target_task_id);
+        *ent.get_mut() = S::success();
+        self.emit_transition(&target_task_id, S::revoke_failed(), S::success(), TransitionCause::Forced);
+        drop(ent);
+
+        self.on_state_changed(&target_task_id);
+        Ok(())
+    }
+
+    /// The state of a task whose revoking fails, when it was in `restored_state` before revoking,
+    /// and whether the reason should be recorded.
+    pub(crate) fn revoke_failed_state(&self, restored_state: S) -> (S, bool) {
+        match self.revoke_failure_policy {
+            RevokeFailurePolicy::Restore => (restored_state, false),
+            RevokeFailurePolicy::RevokeFailed => (S::revoke_failed(), true),
+            RevokeFailurePolicy::Failed => (S::failed(), true),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::panic::resume_unwind;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+use crate::*;
+
+/// What to do when revoking a `Working` or `Queued` task by [`AsyncTasksRecorder::revoke_task_even_working`].
+///
+/// Either way, the task becomes `Revoking` at once, so it cannot be launched again before it is revoked.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum WhenWorking {
+    /// Abort the task, and then execute the `Future` for revoking.
+    ///
+    /// The task is back to `Failed` (or the state decided by the [`TransitionTable`]) if the revoking fails.
+    Abort,
+    /// Execute the `Future` for revoking after the task succeeds.
+    ///
+    /// If the task fails, it is removed without revoking.
+    AfterSuccess,
+}
+
+/// Start to execute a `Future` for revoking.
+type StartRevoke<K, S> = Box<dyn FnOnce(&AsyncTasksRecorder<K, S>) + Send>;
+
+/// A revoking which took over a `Working` task, and is started after the task finishes.
+pub(crate) struct PendingRevoke<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    when_working: WhenWorking,
+    /// Wrapped by `Mutex` only to be `Sync`.
+    start: Mutex<StartRevoke<K, S>>,
+}
+
+/// Revoking interfaces of `Working` tasks.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Revoke target task with its `task_id` and a `Future` for revoking, and execute it asynchronously,
+    /// even if the task is `Working` or `Queued`.
+    ///
+    /// Return **immediately**.
+    ///
+    /// Same as [`revoke_task`](Self::revoke_task) if the task can be revoked.
+    /// If the task is `Working` or `Queued`, it becomes `Revoking` at once,
+    /// and the `Future` for revoking is executed as `when_working` says.
+    ///
+    /// `Err` would include the task's current state, or the rejection of the [`TransitionGuard`].
+    pub async fn revoke_task_even_working<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut, when_working: WhenWorking) -> Result<(), (Refusal<S>, Fut)>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + Clone + Send + Sync + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let task_id = target_task_id.clone();
+        self.try_mark_revoking_even_working(target_task_id, revoke_task, when_working, move |recorder, revoke_task| {
+            recorder.spawn_revoking_task(task_id, revoke_task, None);
+        }).await
+    }
+
+    /// Revoke target task with its `task_id` and a `Future` for revoking, even if the task is `Working` or `Queued`.
+    ///
+    /// Not return (keep awaiting) until the revoking finishes when successfully start to revoke.
+    ///
+    /// See [`revoke_task_even_working`](Self::revoke_task_even_working).
+    /// The `Future` for revoking is executed asynchronously,
+    /// so dropping this `Future` would not stop the revoking.
+    ///
+    /// Return `Ok(None)` if the `Future` for revoking is not executed,
+    /// i.e. the task failed with [`WhenWorking::AfterSuccess`].
+    pub async fn revoke_task_even_working_block<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut, when_working: WhenWorking) -> Result<Option<Result<R, E>>, (Refusal<S>, Fut)>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + Clone + Send + Sync + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send + 'static,
+              E: Send + 'static {
+        let task_id = target_task_id.clone();
+        let (res_tx, res_rx) = oneshot::channel();
+        self.try_mark_revoking_even_working(target_task_id, revoke_task, when_working, move |recorder, revoke_task| {
+            let recorder_cloned = recorder.clone();
+            recorder.spawn(async move {
+                let _ = res_tx.send(recorder_cloned.revoke_task_fut(&task_id, revoke_task, None, |_| None).await);
+            });
+        }).await?;
+
+        match res_rx.await {
+            Ok(Ok(res)) => Ok(res),
+            Ok(Err(payload)) => resume_unwind(payload),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Crate-level tools.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Start the revoking which took over the target task after the task finishes.
+    ///
+    /// Should be called instead of changing the state when a finishing task is found `Revoking`.
+    pub(crate) fn finish_taken_over<Q>(&self, task_id: &Q, succeeded: bool)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let Some((task_id, pending)) = self.pending_revokes.remove(task_id) else {
+            return;
+        };
+        let start = pending.start.into_inner().unwrap_or_else(|e| e.into_inner());
+
+        let restored_state = match (succeeded, pending.when_working) {
+            (true, _) => self.succeeded_state(&task_id),
+            (false, WhenWorking::Abort) => self.failed_state(&task_id, &FailureReason::Aborted),
+            (false, WhenWorking::AfterSuccess) => {
+                // nothing to revoke
+                self.clear_run_records::<K>(&task_id);
+                self.discard_compensation::<K>(&task_id);
+                let removed = self.recorder.remove_if(&task_id, |v| {
+                    if *v != S::revoking() {
+                        return false;
+                    }
+                    self.emit_transition(&task_id, S::revoking(), S::not_found(), TransitionCause::Revoked);
+                    true
+                });
+                if let Some((task_id, _)) = removed {
+                    self.on_entry_removed(&task_id);
+                    self.on_state_changed::<K>(&task_id);
+                }
+                return;
+            }
+        };
+        if restored_state != S::success() {
+            self.revoking_from.upsert(task_id, restored_state);
+        }
+        start(self);
+    }
+
+    /// Change the task's state to `Revoking` atomically when it can be revoked, or it is `Working` or `Queued`.
+    ///
+    /// `start` is called to execute `revoke_task` at once, or after the taken over task finishes.
+    async fn try_mark_revoking_even_working<Q, Fut, F>(&self, target_task_id: &Q, revoke_task: Fut, when_working: WhenWorking, start: F) -> Result<(), (Refusal<S>, Fut)>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized,
+              Fut: Send + 'static,
+              F: FnOnce(&Self, Fut) + Send + 'static {
+        let mut guard_lock = None;
+        let mut checked = false;
+        loop {
+            let Some(mut ent) = self.recorder.get_async(target_task_id).await else {
+                return Err((Refusal::State(S::not_found()), revoke_task));
+            };
+            let old_state = ent.get().clone();
+            if old_state != S::working() && old_state != S::queued() {
+                drop(ent);
+                // checked again by `try_mark_revoking`, which takes the lock itself
+                drop(guard_lock.take());
+                checked = false;
+                match self.try_mark_revoking(target_task_id).await {
+                    Ok(()) => {
+                        start(self, revoke_task);
+                        return Ok(());
+                    }
+                    // launched again just now
+                    Err(Refusal::State(state)) if state == S::working() || state == S::queued() => continue,
+                    Err(reason) => return Err((reason, revoke_task)),
+                }
+            }
+
+            // the guards are checked without locking the entry,
+            // and the state is checked again afterwards
+            if !self.guards.is_empty() && !checked {
+                let task_id = ent.key().clone();
+                drop(ent);
+                match guard_lock {
+                    None => guard_lock = Some(self.lock_for_guards(task_id).await),
+                    Some(_) => {
+                        if let Err(reason) = self.check_revoke_guards(&task_id).await {
+                            return Err((reason, revoke_task));
+                        }
+                        checked = true;
+                    }
+                }
+                continue;
+            }
+
+            // the pending revoking is registered while the entry is locked,
+            // so the task can always find it when it finishes
+            *ent.get_mut() = S::revoking();
+            let task_id = ent.key().clone();
+            self.pending_revokes.upsert(task_id.clone(), PendingRevoke {
+                when_working,
+                start: Mutex::new(Box::new(move |recorder| start(recorder, revoke_task))),
+            });
+            let canceller = match when_working {
+                WhenWorking::Abort => self.cancellers.remove(&task_id).map(|(_, canceller)| canceller),
+                WhenWorking::AfterSuccess => None,
+            };
+            self.observe(|o| o.on_revoke_start(&task_id));
+            self.emit_transition(&task_id, old_state, S::revoking(), TransitionCause::RevokeStarted);
+            drop(ent);
+            drop(guard_lock);
+
+            self.on_state_changed::<K>(&task_id);
+
+            // the `Future` for revoking is started after the aborted task finishes
+            if let Some(canceller) = canceller {
+                canceller.cancel().await;
+            }
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::future::{pending, Future};
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Instant;
+use crate::*;
+
+/// Runtime interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Set the [`Spawner`] to execute the tasks in the background and to set timers.
+    ///
+    /// [`TokioSpawner`] is used by default if feature `runtime-tokio` is enabled.
+    /// Otherwise, a spawner must be set before launching or revoking any task.
+    pub fn with_spawner<T>(mut self, spawner: T) -> Self
+        where T: Spawner {
+        self.spawner = Some(Arc::new(spawner));
+        self
+    }
+
+    /// Spawn the tasks on the runtime of `handle`, no matter where the methods spawning are called.
+    ///
+    /// Same as `with_spawner(TokioSpawner::with_handle(handle))`.
+    #[cfg(feature = "runtime-tokio")]
+    pub fn with_runtime_handle(self, handle: tokio::runtime::Handle) -> Self {
+        self.with_spawner(TokioSpawner::with_handle(handle))
+    }
+
+    /// Execute `fut` in the background by the spawner.
+    pub(crate) fn spawn<Fut>(&self, fut: Fut)
+        where Fut: Future<Output=()> + Send + 'static {
+        self.get_spawner().spawn(Box::pin(fut));
+    }
+
+    /// Sleep until the `deadline`, or never wake up if there is no deadline.
+    ///
+    /// The timer is not created until polled, so no timer is required without deadline.
+    pub(crate) async fn sleep_until_deadline(&self, deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => self.get_spawner().sleep_until(deadline).await,
+            None => pending().await,
+        }
+    }
+
+    #[cfg(feature = "runtime-tokio")]
+    fn get_spawner(&self) -> &dyn Spawner {
+        static DEFAULT_SPAWNER: TokioSpawner = TokioSpawner::new();
+        match &self.spawner {
+            Some(spawner) => spawner.as_ref(),
+            None => &DEFAULT_SPAWNER,
+        }
+    }
+
+    #[cfg(not(feature = "runtime-tokio"))]
+    fn get_spawner(&self) -> &dyn Spawner {
+        match &self.spawner {
+            Some(spawner) => spawner.as_ref(),
+            None => panic!("No spawner is set. Enable feature `runtime-tokio` or call `with_spawner`"),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::hash::Hash;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::*;
+
+/// Decide the states of the tasks restored from a snapshot,
+/// whose `Future`s no longer exist.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct RestoreOptions<S = TaskState> {
+    /// The state of the tasks which were `Working` or `Queued`. `Failed` by default.
+    ///
+    /// The tasks would not be restored if it is `NotFound`.
+    pub working_as: S,
+    /// The state of the tasks which were `Revoking`. `Success` by default,
+    /// just like the revoking fails.
+    ///
+    /// The tasks would not be restored if it is `NotFound`.
+    pub revoking_as: S,
+}
+
+impl<S> Default for RestoreOptions<S>
+    where S: RecordedState {
+    fn default() -> Self {
+        RestoreOptions {
+            working_as: S::failed(),
+            revoking_as: S::success(),
+        }
+    }
+}
+
+impl<S> RestoreOptions<S>
+    where S: RecordedState {
+    /// Map the state in a snapshot to the state to restore.
+    pub fn map_state(&self, state: S) -> S {
+        if state == S::working() || state == S::queued() {
+            self.working_as.clone()
+        } else if state == S::revoking() {
+            self.revoking_as.clone()
+        } else {
+            state
+        }
+    }
+}
+
+/// The serialized form of the map.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct Snapshot<K, S> {
+    tasks: Vec<(K, S)>,
+}
+
+/// Snapshot interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Get all the tasks and their states.
+    ///
+    /// The tasks are not read atomically,
+    /// so the tasks changed during snapshotting may be in either the old or the new state.
+    pub async fn snapshot(&self) -> Vec<(K, S)> {
+        let mut tasks = Vec::with_capacity(self.recorder.len());
+        self.recorder.scan_async(|k, v| tasks.push((k.clone(), v.clone()))).await;
+        tasks
+    }
+
+    /// Create by the tasks and their states got by [`snapshot`](Self::snapshot).
+    ///
+    /// The `Working` and `Revoking` tasks are mapped by `options`,
+    /// because their `Future`s no longer exist.
+    pub fn restore(tasks: impl IntoIterator<Item=(K, S)>, options: &RestoreOptions<S>) -> Self {
+        let recorder = scc::HashMap::new();
+        for (task_id, state) in tasks {
+            let state = options.map_state(state);
+            if state == S::not_found() {
+                continue;
+            }
+            recorder.upsert(task_id, state);
+        }
+
+        Self::new_with_task_manager(recorder)
+    }
+
+    /// Write all the tasks and their states to `writer` as JSON.
+    ///
+    /// See [`snapshot`](Self::snapshot).
+    #[cfg(feature = "serde")]
+    pub async fn snapshot_to<W>(&self, writer: W) -> io::Result<()>
+        where K: Serialize,
+              S: Serialize,
+              W: io::Write {
+        let snapshot = Snapshot {
+            tasks: self.snapshot().await,
+        };
+        serde_json::to_writer(writer, &snapshot).map_err(io::Error::from)
+    }
+
+    /// Create by a snapshot written by [`snapshot_to`](Self::snapshot_to).
+    ///
+    /// The `Working` and `Revoking` tasks are mapped by `options`,
+    /// because their `Future`s no longer exist.
+    #[cfg(feature = "serde")]
+    pub fn restore_from<Rd>(reader: Rd, options: &RestoreOptions<S>) -> io::Result<Self>
+        where K: DeserializeOwned,
+              S: DeserializeOwned,
+              Rd: io::Read {
+        let snapshot: Snapshot<K, S> = serde_json::from_reader(reader).map_err(io::Error::from)?;
+        Ok(Self::restore(snapshot.tasks, options))
+    }
+}
@@ -0,0 +1,178 @@
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::oneshot;
+use crate::*;
+
+/// Why a task awaited by a [`TaskHandle`] does not succeed.
+#[derive(Debug)]
+pub enum TaskError<E> {
+    /// The task returned `Err`.
+    Error(E),
+    /// The task panicked. The panic is not passed to the hook set by
+    /// [`with_panic_hook`](AsyncTasksRecorder::with_panic_hook) unless the handle has been dropped.
+    Panicked(PanicPayload),
+    /// The task was aborted.
+    Aborted,
+    /// The task did not finish before its deadline.
+    TimedOut,
+    /// A prerequisite of the task failed.
+    DependencyFailed,
+    /// The `Future` executing the task was dropped, e.g. the runtime is shutting down.
+    Dropped,
+}
+
+impl<E> TaskError<E> {
+    /// Get the [`FailureReason`] recorded for the task.
+    pub fn reason(&self) -> FailureReason {
+        match self {
+            TaskError::Error(_) => FailureReason::Error,
+            TaskError::Panicked(_) => FailureReason::Panicked,
+            TaskError::Aborted => FailureReason::Aborted,
+            TaskError::TimedOut => FailureReason::TimedOut,
+            TaskError::DependencyFailed => FailureReason::DependencyFailed,
+            TaskError::Dropped => FailureReason::Dropped,
+        }
+    }
+
+    /// Get the error if the task returned `Err`.
+    pub fn into_error(self) -> Option<E> {
+        match self {
+            TaskError::Error(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<E> std::fmt::Display for TaskError<E>
+    where E: std::fmt::Display {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskError::Error(e) => e.fmt(f),
+            TaskError::Panicked(_) => write!(f, "task panicked"),
+            TaskError::Aborted => write!(f, "task aborted"),
+            TaskError::TimedOut => write!(f, "task timed out"),
+            TaskError::DependencyFailed => write!(f, "prerequisite of task failed"),
+            TaskError::Dropped => write!(f, "task dropped"),
+        }
+    }
+}
+
+impl<E> std::error::Error for TaskError<E>
+    where E: std::error::Error + 'static {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TaskError::Error(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An owned permission to await the output of a task launched by [`AsyncTasksRecorder::launch_with_handle`].
+///
+/// Output `Err` with a [`TaskError`] if the task does not succeed.
+///
+/// The task keeps running if the handle is dropped, like a `JoinHandle` of `tokio`.
+pub struct TaskHandle<K, R, E, S = TaskState>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    recorder: AsyncTasksRecorder<K, S>,
+    task_id: K,
+    /// The run of the task awaited by this handle.
+    run_id: u64,
+    output_rx: oneshot::Receiver<Result<R, TaskError<E>>>,
+}
+
+impl<K, R, E, S> TaskHandle<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// The `task_id` of the task.
+    pub fn task_id(&self) -> &K {
+        &self.task_id
+    }
+
+    /// Query the state of the task.
+    pub async fn state(&self) -> S {
+        self.recorder.query_task_state(&self.task_id).await
+    }
+
+    /// Abort the task if it is `Working` or `Queued`. See [`AsyncTasksRecorder::abort_task`].
+    ///
+    /// The handle would output [`TaskError::Aborted`].
+    /// Only the run awaited by this handle is aborted, not the one launched again after it finished.
+    pub async fn abort(&self) -> Result<(), S> {
+        self.recorder.abort_run(&self.task_id, self.run_id).await
+    }
+
+    /// Drop the handle and let the task keep running. Its output would be discarded.
+    pub fn detach(self) {}
+}
+
+impl<K, R, E, S> Future for TaskHandle<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    type Output = Result<R, TaskError<E>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // the sender is dropped without sending if the `Future` executing the task is dropped
+        Pin::new(&mut self.output_rx).poll(cx).map(|res| res.unwrap_or(Err(TaskError::Dropped)))
+    }
+}
+
+// `task_id` is never pinned
+impl<K, R, E, S> Unpin for TaskHandle<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {}
+
+impl<K, R, E, S> std::fmt::Debug for TaskHandle<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + std::fmt::Debug + 'static,
+          S: RecordedState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskHandle")
+            .field("task_id", &self.task_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Handle interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Launch a task and execute it asynchronously, and return a [`TaskHandle`] to await its output.
+    ///
+    /// Return **immediately**.
+    ///
+    /// Same as [`launch`](Self::launch), but the output of the task can be got by awaiting the handle.
+    /// The panic of the task is passed to the handle if it is still held.
+    /// The task is launched as `options` says, see [`LaunchOptions`].
+    pub async fn launch_with_handle<Fut, R, E>(&self, task_id: K, task: Fut, options: LaunchOptions<K>) -> Result<TaskHandle<K, R, E, S>, (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send + 'static,
+              E: Send + 'static {
+        let ticket = match self.try_mark_working_with(&task_id, options).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+
+        // start
+        let run_id = ticket.run_id;
+        let (output_tx, output_rx) = oneshot::channel();
+        let recorder = self.clone();
+        let task_id_cloned = task_id.clone();
+        self.spawn(async move {
+            let res = recorder.run_task_fut(task_id_cloned.clone(), task, ticket, |_| ()).await;
+            // the handle has been dropped
+            if let Err(Err(TaskError::Panicked(payload))) = output_tx.send(res) {
+                recorder.handle_panic(&task_id_cloned, payload);
+            }
+        });
+
+        Ok(TaskHandle {
+            recorder: self.clone(),
+            task_id,
+            run_id,
+            output_rx,
+        })
+    }
+}
@@ -0,0 +1,61 @@
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::panic::resume_unwind;
+use std::time::Duration;
+use std::time::Instant;
+use crate::*;
+
+/// Timeout interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Revoke target task with a timeout, and execute the `Future` for revoking asynchronously.
+    ///
+    /// Same as [`revoke_task`](Self::revoke_task),
+    /// but the `Future` would be dropped if it does not finish within `timeout`,
+    /// and the task would be handled just like the `Future` fails, see [`RevokeFailurePolicy`].
+    /// There is no timeout if `timeout` is too large to represent.
+    pub async fn revoke_task_with_timeout<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut, timeout: Duration) -> Result<(), (Refusal<S>, Fut)>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + Clone + Send + Sync + 'static,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let deadline = Instant::now().checked_add(timeout);
+        if let Err(reason) = self.try_mark_revoking(target_task_id).await {
+            return Err((reason, revoke_task));
+        }
+
+        // start to revoke
+        self.spawn_revoking_task(target_task_id.clone(), revoke_task, deadline);
+
+        Ok(())
+    }
+
+    /// Revoke target task with a timeout.
+    ///
+    /// Same as [`revoke_task_block`](Self::revoke_task_block),
+    /// but the `Future` would be dropped if it does not finish within `timeout`,
+    /// and the task would be handled just like the `Future` fails, see [`RevokeFailurePolicy`].
+    /// There is no timeout if `timeout` is too large to represent.
+    ///
+    /// Return `Ok(None)` if the `Future` is timed out.
+    pub async fn revoke_task_block_with_timeout<Q, Fut, R, E>(&self, target_task_id: &Q, revoke_task: Fut, timeout: Duration) -> Result<Option<Result<R, E>>, (Refusal<S>, Fut)>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized,
+              Fut: Future<Output=Result<R, E>> + Send + 'static,
+              R: Send,
+              E: Send {
+        let deadline = Instant::now().checked_add(timeout);
+        if let Err(reason) = self.try_mark_revoking(target_task_id).await {
+            return Err((reason, revoke_task));
+        }
+
+        // start to revoke (block)
+        match self.revoke_task_fut(target_task_id, revoke_task, deadline, |_| None).await {
+            Ok(res) => Ok(res),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::hash::Hash;
+use std::sync::Arc;
+use scc::hash_map::Entry;
+use crate::*;
+
+/// Decide which transitions of the settled states (i.e. not `Working`, `Queued` or `Revoking`) are allowed.
+///
+/// Register by [`AsyncTasksRecorder::with_transition_table`].
+/// The default methods make the rules of [`DefaultTransitions`]:
+/// launch from `NotFound` or `Failed`, succeed to `Success`, fail to `Failed`,
+/// revoke from `Success` or `RevokeFailed`, and nothing else.
+///
+/// The user-defined states of `S` (see [`RecordedState`]) are never entered by the recorder itself
+/// unless the table says so, e.g. a task can succeed to a state waiting for approval,
+/// and then be moved to `Success` by [`transit`](AsyncTasksRecorder::transit).
+///
+/// The in-progress states (`Working`, `Queued` and `Revoking`) are always managed by the recorder,
+/// so the table is never asked about them.
+pub trait TransitionTable<K, S = TaskState>: Send + Sync + 'static
+    where S: RecordedState {
+    /// Whether a task in `state` can be launched.
+    fn can_launch(&self, task_id: &K, state: &S) -> bool {
+        let _ = task_id;
+        *state == S::not_found() || *state == S::failed()
+    }
+
+    /// The state of a task after it succeeds. Should be a settled state other than `NotFound`.
+    fn succeeded_state(&self, task_id: &K) -> S {
+        let _ = task_id;
+        S::success()
+    }
+
+    /// The state of a task after it fails for `reason`. Should be a settled state other than `NotFound`.
+    ///
+    /// The reason can be got by [`query_failure_reason`](AsyncTasksRecorder::query_failure_reason) in the returned state.
+    fn failed_state(&self, task_id: &K, reason: &FailureReason) -> S {
+        let _ = (task_id, reason);
+        S::failed()
+    }
+
+    /// Whether a task in `state` can be revoked.
+    ///
+    /// The task is `NotFound` after revoking successfully, otherwise it is back to `state`.
+    fn can_revoke(&self, task_id: &K, state: &S) -> bool {
+        let _ = task_id;
+        *state == S::success() || *state == S::revoke_failed()
+    }
+
+    /// Whether a task can be moved from `from` to `to` by [`transit`](AsyncTasksRecorder::transit).
+    fn can_transit(&self, task_id: &K, from: &S, to: &S) -> bool {
+        let _ = (task_id, from, to);
+        false
+    }
+}
+
+/// The default rules of the recorder, for [`TaskState`] and any other [`RecordedState`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTransitions;
+
+impl<K, S> TransitionTable<K, S> for DefaultTransitions
+    where S: RecordedState {}
+
+/// Whether `state` is managed by the recorder, i.e. it would change by itself.
+pub(crate) fn is_in_progress<S>(state: &S) -> bool
+    where S: RecordedState {
+    *state == S::working() || *state == S::queued() || *state == S::revoking()
+}
+
+/// Transition table interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Replace the rules of launching, succeeding, failing and revoking by `table`.
+    ///
+    /// `modify_state_force` and `modify_to_success_before_work` do not follow the table.
+    pub fn with_transition_table<T>(mut self, table: T) -> Self
+        where T: TransitionTable<K, S> {
+        self.transitions = Arc::new(table);
+        self
+    }
+
+    /// Move the target task from its current state to `target_state` atomically if the table allows it.
+    ///
+    /// Moving to `NotFound` removes the task, and moving from `NotFound` inserts it.
+    /// Neither the current state nor `target_state` can be `Working`, `Queued` or `Revoking`.
+    ///
+    /// - Return `Ok(task_state)` if succeed and the task was in `task_state` state.
+    /// - Return `Err(task_state)` if refused and the task is in `task_state` state.
+    pub async fn transit(&self, target_task_id: K, target_state: S) -> Result<S, S> {
+        let entry = self.recorder.entry_async(target_task_id.clone()).await;
+        let old_state = match &entry {
+            Entry::Occupied(ent) => ent.get().clone(),
+            Entry::Vacant(_) => S::not_found(),
+        };
+        if is_in_progress(&old_state) || is_in_progress(&target_state)
+            || !self.transitions.can_transit(&target_task_id, &old_state, &target_state) {
+            return Err(old_state);
+        }
+
+        // the event is sent while the entry is locked
+        self.emit_transition(&target_task_id, old_state.clone(), target_state.clone(), TransitionCause::Transited);
+        match (entry, target_state == S::not_found()) {
+            (Entry::Occupied(ent), true) => {
+                let _ = ent.remove_entry();
+                self.on_entry_removed(&target_task_id);
+            }
+            (Entry::Occupied(mut ent), false) => {
+                *ent.get_mut() = target_state.clone();
+            }
+            (Entry::Vacant(ent), false) => {
+                ent.insert_entry(target_state.clone());
+                self.on_entry_inserted(&target_task_id);
+            }
+            (Entry::Vacant(_), true) => {}
+        }
+        self.clear_run_records(&target_task_id);
+        if target_state == S::not_found() {
+            self.discard_compensation(&target_task_id);
+        }
+        self.on_state_changed(&target_task_id);
+        Ok(old_state)
+    }
+
+    /// Whether a task in `state` can be launched.
+    pub(crate) fn can_launch_from(&self, task_id: &K, state: &S) -> bool {
+        !is_in_progress(state) && self.transitions.can_launch(task_id, state)
+    }
+
+    /// Whether a task in `state` can be revoked.
+    pub(crate) fn can_revoke_from(&self, task_id: &K, state: &S) -> bool {
+        state != &S::not_found() && !is_in_progress(state) && self.transitions.can_revoke(task_id, state)
+    }
+
+    /// The state of the target task after it succeeds.
+    pub(crate) fn succeeded_state(&self, task_id: &K) -> S {
+        self.transitions.succeeded_state(task_id)
+    }
+
+    /// The state of the target task after it fails for `reason`.
+    pub(crate) fn failed_state(&self, task_id: &K, reason: &FailureReason) -> S {
+        self.transitions.failed_state(task_id, reason)
+    }
+}
@@ -0,0 +1,95 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::pin::pin;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use crate::*;
+use super::is_in_progress;
+
+/// Waiting interfaces.
+impl<K, S> AsyncTasksRecorder<K, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          S: RecordedState {
+    /// Wait until the target task is in one of the `target_states`, and return its state.
+    ///
+    /// Return **immediately** if the task is already in one of the `target_states`.
+    ///
+    /// The waiting is woken up by every change of the task's state, so no polling is needed.
+    /// A task removed by revoking (or by [`modify_state_force`](Self::modify_state_force))
+    /// is regarded as `NotFound`.
+    ///
+    /// A transient state may be missed if it is changed again before the waiting caller is woken up.
+    pub async fn wait_for_state<Q>(&self, task_id: &Q, target_states: &[S]) -> S
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ToOwned<Owned=K> + ?Sized {
+        self.wait_for_state_matching(task_id, |state| target_states.contains(state)).await
+    }
+
+    /// Wait until the target task finishes, and return its state.
+    ///
+    /// Return **immediately** if the task is not `Working`, `Queued` or `Revoking`.
+    ///
+    /// - `Success` if the task (or the revoking) succeeded.
+    /// - `Failed` if the task failed.
+    /// - `NotFound` if the task has never been launched or has been revoked.
+    /// - A user-defined state if the [`TransitionTable`] makes a succeeded or failed task enter it.
+    pub async fn wait_for_finish<Q>(&self, task_id: &Q) -> S
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ToOwned<Owned=K> + ?Sized {
+        self.wait_for_state_matching(task_id, |state| !is_in_progress(state)).await
+    }
+
+    /// Wait until the state of the target task matches `predicate`, and return its state.
+    pub(crate) async fn wait_for_state_matching<Q, F>(&self, task_id: &Q, predicate: F) -> S
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ToOwned<Owned=K> + ?Sized,
+              F: Fn(&S) -> bool {
+        let guard = NotifierGuard::new(&self.notifiers, task_id).await;
+
+        loop {
+            // register before query, so that no change would be missed
+            let mut notified = pin!(guard.notifier.notified());
+            notified.as_mut().enable();
+
+            let state = self.query_task_state(task_id).await;
+            if predicate(&state) {
+                return state;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+/// Hold a notifier of a task, and remove it from the map when no one is waiting.
+struct NotifierGuard<'a, K, Q>
+    where K: Eq + Hash + Borrow<Q>,
+          Q: Hash + Eq + ?Sized {
+    notifiers: &'a scc::HashMap<K, Arc<Notify>>,
+    task_id: &'a Q,
+    notifier: Arc<Notify>,
+}
+
+impl<'a, K, Q> NotifierGuard<'a, K, Q>
+    where K: Eq + Hash + Borrow<Q>,
+          Q: Hash + Eq + ToOwned<Owned=K> + ?Sized {
+    async fn new(notifiers: &'a scc::HashMap<K, Arc<Notify>>, task_id: &'a Q) -> Self {
+        let notifier = notifiers.entry_async(task_id.to_owned()).await
+            .or_insert_with(|| Arc::new(Notify::new()))
+            .get().clone();
+        NotifierGuard {
+            notifiers,
+            task_id,
+            notifier,
+        }
+    }
+}
+
+impl<K, Q> Drop for NotifierGuard<'_, K, Q>
+    where K: Eq + Hash + Borrow<Q>,
+          Q: Hash + Eq + ?Sized {
+    fn drop(&mut self) {
+        // only the map and this guard hold the notifier
+        self.notifiers.remove_if(self.task_id, |v| Arc::strong_count(v) <= 2);
+    }
+}
@@ -0,0 +1,333 @@
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::panic::resume_unwind;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use crate::*;
+
+/// An [`AsyncTasksRecorder`] which also keeps the output of the last finished run of each task.
+///
+/// All tasks hosted by one `AsyncTasksResultRecorder` must return the same `Result<R, E>`.
+///
+/// The output of a task is stored before the task becomes `Success` or `Failed`,
+/// and is removed when the task is launched again or revoked successfully.
+///
+/// Thread-safe. Can be shared by `cloning` (`Arc` is used internally).
+#[derive(Debug)]
+pub struct AsyncTasksResultRecorder<K, R, E, S = TaskState>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          R: Send + Sync + 'static,
+          E: Send + Sync + 'static,
+          S: RecordedState {
+    recorder: AsyncTasksRecorder<K, S>,
+    results: Arc<scc::HashMap<K, Arc<Result<R, E>>>>,
+}
+
+impl<K, R, E> AsyncTasksResultRecorder<K, R, E>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          R: Send + Sync + 'static,
+          E: Send + Sync + 'static {
+    /// Create a completely new `AsyncTasksResultRecorder`.
+    ///
+    /// Use [`default`](Self::default) to record the states other than [`TaskState`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Public interfaces.
+impl<K, R, E, S> AsyncTasksResultRecorder<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          R: Send + Sync + 'static,
+          E: Send + Sync + 'static,
+          S: RecordedState {
+    /// Create by an `AsyncTasksRecorder`.
+    ///
+    /// The tasks already in `recorder` have no output.
+    /// The output of a task is removed when it expires, see [`AsyncTasksRecorder::with_success_ttl`].
+    pub fn new_with_recorder(recorder: AsyncTasksRecorder<K, S>) -> Self {
+        let results: Arc<scc::HashMap<K, Arc<Result<R, E>>>> = scc::HashMap::new().into();
+        let results_cloned = results.clone();
+        AsyncTasksResultRecorder {
+            recorder: recorder.with_expire_cleanup(move |task_id| {
+                results_cloned.remove(task_id);
+            }),
+            results,
+        }
+    }
+
+    /// Launch a task and execute it asynchronously.
+    ///
+    /// Return **immediately**.
+    ///
+    /// Same as [`AsyncTasksRecorder::launch`], but the output of the task would be stored.
+    pub async fn launch<Fut>(&self, task_id: K, task: Fut) -> Result<(), (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static {
+        let ticket = match self.recorder.try_mark_working(&task_id).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+        self.results.remove_async(&task_id).await;
+
+        // start
+        let recorder = self.clone();
+        self.recorder.spawn(async move {
+            if let Err(payload) = recorder.launch_task_fut(task_id.clone(), task, ticket).await {
+                recorder.recorder.handle_panic(&task_id, payload);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Launch a task.
+    ///
+    /// Not return (keep awaiting) until the task finishes when successfully launch.
+    ///
+    /// Same as [`AsyncTasksRecorder::launch_block`], but the output of the task would be stored,
+    /// and a shared reference of it would be returned.
+    pub async fn launch_block<Fut>(&self, task_id: K, task: Fut) -> Result<Option<Arc<Result<R, E>>>, (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static {
+        let ticket = match self.recorder.try_mark_working(&task_id).await {
+            Ok(ticket) => ticket,
+            Err(reason) => return Err((reason, task)),
+        };
+        self.results.remove_async(&task_id).await;
+
+        // start (block)
+        match self.launch_task_fut(task_id, task, ticket).await {
+            Ok(res) => Ok(res),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+
+    /// Launch a task, or join the run of the task which is already launched.
+    ///
+    /// Not return (keep awaiting) until the run finishes, and return a shared reference of its output.
+    ///
+    /// - If the task can be launched, `task` is executed asynchronously,
+    ///   so dropping this `Future` would not affect the callers joining it.
+    /// - If the task is `Queued` or `Working`, `task` is dropped and the running one is waited for.
+    /// - If the task is `Success`, its stored output is returned **immediately**.
+    ///
+    /// Return `Ok(None)` if the run has no output, e.g. it is aborted or panics.
+    /// A joining caller gets the output stored when it is woken up,
+    /// which may be newer if the task has finished again since then.
+    ///
+    /// **Immediately** return `Err` when the task is in another state (e.g. `Revoking`),
+    /// a guard rejects, or a quota of its group is exceeded.
+    pub async fn get_or_launch<Fut>(&self, task_id: K, task: Fut) -> Result<Option<Arc<Result<R, E>>>, (Refusal<S>, Fut)>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static {
+        let refusal = match self.recorder.try_mark_working(&task_id).await {
+            Ok(ticket) => {
+                self.results.remove_async(&task_id).await;
+
+                // start, and wait for the output
+                let (res_tx, res_rx) = oneshot::channel();
+                let recorder = self.clone();
+                self.recorder.spawn(async move {
+                    match recorder.launch_task_fut(task_id.clone(), task, ticket).await {
+                        Ok(res) => {
+                            let _ = res_tx.send(res);
+                        }
+                        Err(payload) => recorder.recorder.handle_panic(&task_id, payload),
+                    }
+                });
+                return Ok(res_rx.await.ok().flatten());
+            }
+            Err(refusal) => refusal,
+        };
+
+        match refusal.state() {
+            Some(state) if *state == S::success() => {}
+            Some(state) if *state == S::queued() || *state == S::working() => {
+                self.recorder.wait_for_finish(&task_id).await;
+            }
+            _ => return Err((refusal, task)),
+        }
+        Ok(self.results.read_async(&task_id, |_, v| v.clone()).await)
+    }
+
+    /// Abort a `Working` task, and make it `Failed`.
+    ///
+    /// See [`AsyncTasksRecorder::abort_task`]. The output of the aborted task would not be stored.
+    pub async fn abort_task<Q>(&self, task_id: &Q) -> Result<(), S>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.recorder.abort_task(task_id).await
+    }
+
+    /// Query the target task's state.
+    pub async fn query_task_state<Q>(&self, task_id: &Q) -> S
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        self.recorder.query_task_state(task_id).await
+    }
+
+    /// Query the target task's state and a shared reference of its stored output.
+    ///
+    /// The output is `None` if the task has not finished since it was launched.
+    ///
+    /// The state and the output are not read atomically.
+    /// When the state is `Success` or `Failed`,
+    /// the output is at least as new as the state.
+    pub async fn query_task_result<Q>(&self, task_id: &Q) -> (S, Option<Arc<Result<R, E>>>)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized {
+        let state = self.recorder.query_task_state(task_id).await;
+        if state == S::not_found() {
+            return (state, None);
+        }
+        let res = self.results.read_async(task_id, |_, v| v.clone()).await;
+        (state, res)
+    }
+
+    /// Query the target task's state and a clone of its stored output.
+    ///
+    /// See [`query_task_result`](Self::query_task_result).
+    pub async fn query_task_result_cloned<Q>(&self, task_id: &Q) -> (S, Option<Result<R, E>>)
+        where K: Borrow<Q>,
+              Q: Hash + Eq + ?Sized,
+              R: Clone,
+              E: Clone {
+        let (state, res) = self.query_task_result(task_id).await;
+        (state, res.map(|res| res.as_ref().clone()))
+    }
+
+    /// Revoke target task with its `task_id` and a `Future` for revoking,  and execute it asynchronously.
+    ///
+    /// Return **immediately**.
+    ///
+    /// Same as [`AsyncTasksRecorder::revoke_task`].
+    /// The stored output would be removed when the revoking succeeds.
+    pub async fn revoke_task<Q, Fut, RR, RE>(&self, target_task_id: &Q, revoke_task: Fut) -> Result<(), (Refusal<S>, Fut)>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + Clone + Send + Sync + 'static,
+              Fut: Future<Output=Result<RR, RE>> + Send + 'static,
+              RR: Send + 'static,
+              RE: Send + Sync + 'static {
+        if let Err(reason) = self.recorder.try_mark_revoking(target_task_id).await {
+            return Err((reason, revoke_task));
+        }
+
+        // start to revoke
+        let revoke_task = Self::clear_result_fut(self.results.clone(), target_task_id.clone(), revoke_task);
+        self.recorder.spawn_revoking_task(target_task_id.clone(), revoke_task, None);
+
+        Ok(())
+    }
+
+    /// Revoke target task with its `task_id` and a `Future` for revoking.
+    ///
+    /// Not return (keep awaiting) until the task finishes when successfully start to revoke.
+    ///
+    /// Same as [`AsyncTasksRecorder::revoke_task_block`].
+    /// The stored output would be removed when the revoking succeeds.
+    pub async fn revoke_task_block<Q, Fut, RR, RE>(&self, target_task_id: &Q, revoke_task: Fut) -> Result<Result<RR, RE>, (Refusal<S>, Fut)>
+        where K: Borrow<Q>,
+              Q: Hash + Eq + Clone + Send + Sync + 'static,
+              Fut: Future<Output=Result<RR, RE>> + Send + 'static,
+              RR: Send + 'static,
+              RE: Send + 'static {
+        if let Err(reason) = self.recorder.try_mark_revoking(target_task_id).await {
+            return Err((reason, revoke_task));
+        }
+
+        // start to revoke (block)
+        let revoke_task = Self::clear_result_fut(self.results.clone(), target_task_id.clone(), revoke_task);
+        match self.recorder.revoke_task_fut(target_task_id, revoke_task, None, |_| None).await {
+            Ok(Some(res)) => Ok(res),
+            Ok(None) => unreachable!("revoking without deadline is timed out"),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+
+    /// Modify task's state atomically and forcefully. Not usually used.
+    ///
+    /// See [`AsyncTasksRecorder::modify_state_force`].
+    /// The stored output would be removed if `target_state == TaskState::NotFound`.
+    pub async fn modify_state_force(&self, target_task_id: K, target_state: S) {
+        if target_state == S::not_found() {
+            self.results.remove_async(&target_task_id).await;
+        }
+        self.recorder.modify_state_force(target_task_id, target_state).await;
+    }
+
+    /// Get a reference of the internal `AsyncTasksRecorder`.
+    pub fn get_recorder_ref(&self) -> &AsyncTasksRecorder<K, S> {
+        &self.recorder
+    }
+
+    /// Get a reference of the internal map of outputs.
+    pub fn get_results_ref(&self) -> &scc::HashMap<K, Arc<Result<R, E>>> {
+        &self.results
+    }
+}
+
+impl<K, R, E, S> Clone for AsyncTasksResultRecorder<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          R: Send + Sync + 'static,
+          E: Send + Sync + 'static,
+          S: RecordedState {
+    fn clone(&self) -> Self {
+        AsyncTasksResultRecorder {
+            recorder: self.recorder.clone(),
+            results: self.results.clone(),
+        }
+    }
+}
+
+impl<K, R, E, S> Default for AsyncTasksResultRecorder<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          R: Send + Sync + 'static,
+          E: Send + Sync + 'static,
+          S: RecordedState {
+    fn default() -> Self {
+        Self::new_with_recorder(AsyncTasksRecorder::default())
+    }
+}
+
+/// Private tools.
+impl<K, R, E, S> AsyncTasksResultRecorder<K, R, E, S>
+    where K: Eq + Hash + Clone + Send + Sync + 'static,
+          R: Send + Sync + 'static,
+          E: Send + Sync + 'static,
+          S: RecordedState {
+    /// Execute a launched task, and store its output before its state is changed.
+    async fn launch_task_fut<Fut>(&self, task_id: K, task: Fut, ticket: LaunchTicket) -> Result<Option<Arc<Result<R, E>>>, PanicPayload>
+        where Fut: Future<Output=Result<R, E>> + Send + 'static {
+        let task = async move {
+            let task_res = Arc::new(task.await);
+            if task_res.is_ok() {
+                Ok(task_res)
+            } else {
+                Err(task_res)
+            }
+        };
+
+        let task_res = self.recorder.launch_task_fut(task_id.clone(), task, ticket, |res| {
+            let (Ok(res) | Err(res)) = res;
+            self.results.upsert(task_id.clone(), res.clone());
+        }).await;
+
+        task_res.map(|res| res.map(|(Ok(res) | Err(res))| res))
+    }
+
+    /// Wrap a `Future` for revoking to remove the stored output before the task is removed.
+    async fn clear_result_fut<Q, Fut, RR, RE>(
+        results: Arc<scc::HashMap<K, Arc<Result<R, E>>>>,
+        target_task_id: Q, revoke_task: Fut)
+        -> Result<RR, RE>
+        where K: Borrow<Q>,
+              Q: Hash + Eq,
+              Fut: Future<Output=Result<RR, RE>> + Send + 'static {
+        let revoke_res = revoke_task.await;
+
+        if revoke_res.is_ok() {
+            results.remove_async(&target_task_id).await;
+        }
+
+        revoke_res
+    }
+}
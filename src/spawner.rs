@@ -0,0 +1,100 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Instant;
+
+/// A `Future` which can be sent to other threads.
+pub type BoxFuture = Pin<Box<dyn Future<Output=()> + Send + 'static>>;
+
+/// The async runtime used to execute tasks in the background and to set timers.
+///
+/// [`TokioSpawner`] (feature `runtime-tokio`, enabled by default)
+/// and [`SmolSpawner`] (feature `runtime-smol`) are provided.
+pub trait Spawner: Send + Sync + 'static {
+    /// Execute `fut` in the background.
+    ///
+    /// `fut` should be polled until it finishes, or dropped when the runtime shuts down.
+    fn spawn(&self, fut: BoxFuture);
+
+    /// Return a `Future` which finishes at `deadline`.
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture;
+}
+
+/// Spawn on a `tokio` runtime.
+///
+/// Spawn on the runtime of the caller by `tokio::spawn` by default,
+/// so the methods spawning should be called within a `tokio` runtime.
+/// Spawn on the runtime of a [`Handle`](tokio::runtime::Handle) if it is created by [`with_handle`](Self::with_handle),
+/// no matter where the methods are called.
+#[cfg(feature = "runtime-tokio")]
+#[derive(Debug, Clone, Default)]
+pub struct TokioSpawner {
+    handle: Option<tokio::runtime::Handle>,
+}
+
+#[cfg(feature = "runtime-tokio")]
+impl TokioSpawner {
+    /// Create to spawn on the runtime of the caller.
+    pub const fn new() -> Self {
+        TokioSpawner {
+            handle: None,
+        }
+    }
+
+    /// Create to spawn on the runtime of `handle`.
+    pub fn with_handle(handle: tokio::runtime::Handle) -> Self {
+        TokioSpawner {
+            handle: Some(handle),
+        }
+    }
+}
+
+#[cfg(feature = "runtime-tokio")]
+impl Spawner for TokioSpawner {
+    fn spawn(&self, fut: BoxFuture) {
+        match &self.handle {
+            Some(handle) => {
+                handle.spawn(fut);
+            }
+            None => {
+                tokio::spawn(fut);
+            }
+        }
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture {
+        // the timer is registered to the runtime entered when it is created
+        let _guard = self.handle.as_ref().map(|handle| handle.enter());
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
+
+/// Spawn on an [`async_executor::Executor`], which can be driven by `smol` or any other way,
+/// and set timers by [`async_io::Timer`].
+#[cfg(feature = "runtime-smol")]
+#[derive(Debug, Clone)]
+pub struct SmolSpawner {
+    executor: std::sync::Arc<async_executor::Executor<'static>>,
+}
+
+#[cfg(feature = "runtime-smol")]
+impl SmolSpawner {
+    /// Create by the executor to spawn on.
+    pub fn new(executor: std::sync::Arc<async_executor::Executor<'static>>) -> Self {
+        SmolSpawner {
+            executor,
+        }
+    }
+}
+
+#[cfg(feature = "runtime-smol")]
+impl Spawner for SmolSpawner {
+    fn spawn(&self, fut: BoxFuture) {
+        self.executor.spawn(fut).detach();
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture {
+        Box::pin(async move {
+            async_io::Timer::at(deadline).await;
+        })
+    }
+}
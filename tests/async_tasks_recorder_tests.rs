@@ -53,3 +53,425 @@ fn test_simple_launch_check_revoke_loop_single() {
         test_simple_launch_check_revoke_loop(1000, 30),
     );
 }
+
+#[test]
+fn test_result_launch_check_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_result_launch_check(5000),
+    );
+}
+
+#[test]
+fn test_result_launch_check_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_result_launch_check(1000),
+    );
+}
+
+#[test]
+fn test_result_expiry_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_result_expiry(100),
+    );
+}
+
+#[test]
+fn test_get_or_launch_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_get_or_launch(500, 8),
+    );
+}
+
+#[test]
+fn test_wait_launch_revoke_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_wait_launch_revoke(5000),
+    );
+}
+
+#[test]
+fn test_wait_launch_revoke_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_wait_launch_revoke(1000),
+    );
+}
+
+#[test]
+fn test_abort_working_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_abort_working(),
+    );
+}
+
+#[test]
+fn test_abort_race_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_abort_race(5000),
+    );
+}
+
+#[test]
+fn test_abort_race_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_abort_race(1000),
+    );
+}
+
+#[test]
+fn test_panic_launch_revoke_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_panic_launch_revoke(),
+    );
+}
+
+#[test]
+fn test_drop_launch_block_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_drop_launch_block(),
+    );
+}
+
+#[test]
+fn test_timeout_launch_revoke_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_timeout_launch_revoke(),
+    );
+}
+
+#[test]
+fn test_retry_launch_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_retry_launch(),
+    );
+}
+
+#[test]
+fn test_retry_policy() {
+    test_retry_policy_delay();
+}
+
+#[test]
+fn test_expiry_lazy_and_sweep_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_expiry_lazy_and_sweep(),
+    );
+}
+
+#[test]
+fn test_expiry_sweeper_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_expiry_sweeper(5000),
+    );
+}
+
+#[test]
+fn test_runtime_handle_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_runtime_handle(1000),
+    );
+}
+
+#[test]
+fn test_transition_events_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_transition_events(),
+    );
+}
+
+#[test]
+fn test_transition_events_lagged_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_transition_events_lagged(),
+    );
+}
+
+#[test]
+fn test_transition_events_ordered_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_transition_events_ordered(8, 1000),
+    );
+}
+
+#[test]
+fn test_observer_calls_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_observer_calls(),
+    );
+}
+
+#[test]
+fn test_observer_counts_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_observer_counts(5000),
+    );
+}
+
+#[test]
+fn test_guards_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_guards(),
+    );
+}
+
+#[test]
+fn test_guards_query_recorder_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_guards_query_recorder(),
+    );
+}
+
+#[test]
+fn test_concurrency_limit_fifo_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_concurrency_limit_fifo(),
+    );
+}
+
+#[test]
+fn test_concurrency_limit_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_concurrency_limit(5000, 8),
+    );
+}
+
+#[test]
+fn test_priority_admission_order_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_priority_admission_order(),
+    );
+}
+
+#[test]
+fn test_priority_aging_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_priority_aging(false),
+    );
+}
+
+#[test]
+fn test_priority_aging_before_limit_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_priority_aging(true),
+    );
+}
+
+#[test]
+fn test_group_limits_reject_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_group_limits_reject(),
+    );
+}
+
+#[test]
+fn test_group_limits_queue_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_group_limits_queue(),
+    );
+}
+
+#[test]
+fn test_dependencies_success_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_dependencies_success(),
+    );
+}
+
+#[test]
+fn test_dependencies_failure_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_dependencies_failure(),
+    );
+}
+
+#[test]
+fn test_dependency_cycle_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_dependency_cycle(),
+    );
+}
+
+#[test]
+fn test_progress_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_progress(),
+    );
+}
+
+#[test]
+fn test_snapshot_and_restore_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_snapshot_and_restore(),
+    );
+}
+
+#[test]
+fn test_compensation_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_compensation(1000),
+    );
+}
+
+#[test]
+fn test_compensation_retry_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_compensation_retry(),
+    );
+}
+
+#[test]
+fn test_compensation_factory_panic_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_compensation_factory_panic(),
+    );
+}
+
+#[test]
+fn test_revoke_failed_policy_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_revoke_failed_policy(),
+    );
+}
+
+#[test]
+fn test_revoke_failure_policies_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_revoke_failure_policies(),
+    );
+}
+
+#[test]
+fn test_revoke_working_abort_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_revoke_working_abort(),
+    );
+}
+
+#[test]
+fn test_revoke_working_after_success_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_revoke_working_after_success(),
+    );
+}
+
+#[test]
+fn test_revoke_working_race_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_revoke_working_race(1000),
+    );
+}
+
+#[test]
+fn test_task_handle_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_task_handle(1000),
+    );
+}
+
+#[test]
+fn test_task_handle_abort_and_detach_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_task_handle_abort_and_detach(),
+    );
+}
+
+#[test]
+fn test_transition_table_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_transition_table(),
+    );
+}
+
+#[test]
+fn test_transition_table_failed_state_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_transition_table_failed_state(),
+    );
+}
+
+#[test]
+fn test_default_transitions_refuse_transit_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_default_transitions_refuse_transit(),
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_snapshot_to_and_restore_from_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_snapshot_to_and_restore_from(),
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_journal_replay_single() {
+    do_async_test(
+        RuntimeType::CurrentThread,
+        test_journal_replay(),
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_journal_compaction_multi() {
+    do_async_test(
+        RuntimeType::MultiThread,
+        test_journal_compaction(500, 10),
+    );
+}
+
+#[cfg(feature = "runtime-smol")]
+#[test]
+fn test_smol_spawner_multi() {
+    do_smol_test(|executor| test_smol_spawner(executor, 1000));
+}
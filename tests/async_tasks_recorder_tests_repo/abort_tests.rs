@@ -0,0 +1,90 @@
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_abort_working() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // abort a long task launched by `launch`
+    let task_id = task_id_generator();
+    assert_eq!(manager.abort_task(&task_id).await, Err(TaskState::NotFound));
+    let task = async {
+        tokio::time::sleep(tokio::time::Duration::from_secs(100)).await;
+        Ok::<(), ()>(())
+    };
+    assert!(manager.launch(task_id.clone(), task).await.is_ok());
+    assert_eq!(manager.abort_task(&task_id).await, Ok(()));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed,
+               "Aborted task should be Failed {}", task_id);
+    assert_eq!(manager.abort_task(&task_id).await, Err(TaskState::Failed));
+
+    // relaunch the aborted task
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+    assert_eq!(manager.abort_task(&task_id).await, Err(TaskState::Success),
+               "Finished task shouldn't be aborted {}", task_id);
+
+    // abort a long task launched by `launch_block`
+    let task_id = task_id_generator();
+    let blocked = {
+        let manager = manager.clone();
+        let task_id = task_id.clone();
+        tokio::spawn(async move {
+            let task = async {
+                tokio::time::sleep(tokio::time::Duration::from_secs(100)).await;
+                Ok::<(), ()>(())
+            };
+            manager.launch_block(task_id, task).await
+        })
+    };
+    manager.wait_for_state(&task_id, &[TaskState::Working]).await;
+    assert_eq!(manager.abort_task(&task_id).await, Ok(()));
+    assert!(matches!(blocked.await.unwrap(), Ok(None)),
+            "`launch_block` should see the abortion {}", task_id);
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+}
+
+pub async fn test_abort_race(task_num: usize) {
+    let manager = AsyncTasksResultRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for i in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        let task = async move {
+            let latency = fastrand::u64(0..20);
+            tokio::time::sleep(tokio::time::Duration::from_micros(latency)).await;
+            Ok::<usize, ()>(i)
+        };
+
+        join_set.spawn(async move {
+            assert!(manager.launch(task_id.clone(), task).await.is_ok(),
+                    "Launch should success {}", task_id);
+
+            let latency = fastrand::u64(0..20);
+            tokio::time::sleep(tokio::time::Duration::from_micros(latency)).await;
+
+            match manager.abort_task(&task_id).await {
+                Ok(()) => {
+                    assert_eq!(manager.query_task_result(&task_id).await, (TaskState::Failed, None),
+                               "Aborted task should be Failed without result {}", task_id);
+                }
+                Err(_) => {
+                    assert_eq!(manager.get_recorder_ref().wait_for_finish(&task_id).await, TaskState::Success);
+                    assert_eq!(manager.query_task_result_cloned(&task_id).await, (TaskState::Success, Some(Ok(i))),
+                               "Finished task should keep its result {}", task_id);
+                }
+            }
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+/// Upload a file, which is deleted by its compensation.
+async fn upload(files: Arc<Mutex<HashSet<String>>>, name: String) -> Result<((), Compensation), ()> {
+    tokio::time::sleep(Duration::from_millis(fastrand::u64(1..20))).await;
+    files.lock().unwrap().insert(name.clone());
+
+    let compensation = Compensation::new(move || {
+        let files = files.clone();
+        let name = name.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(fastrand::u64(1..20))).await;
+            files.lock().unwrap().remove(&name);
+            Ok::<(), ()>(())
+        }
+    });
+    Ok(((), compensation))
+}
+
+pub async fn test_compensation(task_num: usize) {
+    let manager = AsyncTasksRecorder::new();
+    let files = Arc::new(Mutex::new(HashSet::new()));
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for _ in 0..task_num {
+        let manager = manager.clone();
+        let files = files.clone();
+        let task_id = task_id_generator();
+
+        join_set.spawn(async move {
+            let res = manager.launch_with_compensation(task_id.clone(), upload(files.clone(), task_id.clone()), LaunchOptions::new()).await;
+            assert!(res.is_ok(), "Launch should success {}", task_id);
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+            assert!(files.lock().unwrap().contains(&task_id));
+
+            // undo by the compensation
+            assert!(manager.revoke(&task_id).await.is_ok(), "Revoke should success {}", task_id);
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::NotFound);
+            assert!(!files.lock().unwrap().contains(&task_id), "File should be deleted {}", task_id);
+
+            // the compensation is removed with the task
+            assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+            let res = manager.revoke(&task_id).await;
+            assert!(matches!(res, Err(Refusal::NoCompensation)), "Should have no compensation {}", task_id);
+            assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+    assert!(files.lock().unwrap().is_empty());
+}
+
+pub async fn test_compensation_retry() {
+    let manager = AsyncTasksRecorder::new()
+        .with_revoke_failure_policy(RevokeFailurePolicy::RevokeFailed);
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // the compensation fails at the first two times
+    let task_id = task_id_generator();
+    let executed = Arc::new(AtomicUsize::new(0));
+    let executed_cloned = executed.clone();
+    let res = manager.launch_block_with_compensation(task_id.clone(), async move {
+        let compensation = Compensation::new(move || {
+            let attempt = executed_cloned.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match attempt {
+                    0 => Err("not yet"),
+                    1 => Err("still not"),
+                    _ => Ok(()),
+                }
+            }
+        });
+        Ok::<_, ()>((42, compensation))
+    }, LaunchOptions::new()).await;
+    assert_eq!(res.ok(), Some(Some(Ok(42))));
+
+    // the error is returned, and the compensation is kept
+    let error = manager.revoke_block(&task_id).await.ok().unwrap().unwrap_err();
+    assert_eq!(error.with_downcast(|e: &&str| *e), Some("not yet"));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::RevokeFailed);
+    assert_eq!(manager.modify_to_success_after_revoke_failed(task_id.clone()).await, Ok(()));
+
+    // the error is recorded
+    assert!(manager.revoke(&task_id).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::RevokeFailed);
+    let error = manager.query_revoke_error(&task_id).await.unwrap();
+    assert_eq!(error.with_downcast(|e: &&str| *e), Some("still not"));
+
+    assert!(matches!(manager.revoke_block(&task_id).await, Ok(Ok(()))));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound);
+    assert_eq!(executed.load(Ordering::SeqCst), 3);
+
+    // nothing to revoke
+    let res = manager.revoke_block(&task_id).await;
+    assert_eq!(res.err().and_then(|refusal| refusal.state().cloned()), Some(TaskState::NotFound));
+
+    // no compensation for a failed task
+    let res = manager.launch_block_with_compensation(task_id.clone(), async {
+        Err::<((), Compensation), _>("upload failed")
+    }, LaunchOptions::new()).await;
+    assert_eq!(res.ok(), Some(Some(Err("upload failed"))));
+    let res = manager.revoke_block(&task_id).await;
+    assert_eq!(res.err().and_then(|refusal| refusal.state().cloned()), Some(TaskState::Failed));
+}
+
+pub async fn test_compensation_factory_panic() {
+    let (panic_tx, mut panic_rx) = tokio::sync::mpsc::unbounded_channel();
+    let manager = AsyncTasksRecorder::new()
+        .with_panic_hook(move |task_id: &String, _| {
+            panic_tx.send(task_id.clone()).unwrap();
+        });
+    let mut task_id_generator = tools::get_task_id_generator();
+    let panicking_compensation = || Compensation::new(|| -> std::future::Ready<Result<(), ()>> {
+        panic!("no compensation");
+    });
+
+    // executed asynchronously
+    let task_id = task_id_generator();
+    let res = manager.launch_block_with_compensation(task_id.clone(), async move {
+        Ok::<_, ()>(((), panicking_compensation()))
+    }, LaunchOptions::new()).await;
+    assert!(matches!(res, Ok(Some(Ok(())))));
+    assert!(manager.revoke(&task_id).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success,
+               "Task should be restored after the factory panics {}", task_id);
+    assert_eq!(panic_rx.recv().await, Some(task_id.clone()));
+
+    // executed in place, and the panic is resumed
+    let manager_cloned = manager.clone();
+    let task_id_cloned = task_id.clone();
+    let res = tokio::spawn(async move {
+        manager_cloned.revoke_block(&task_id_cloned).await
+    }).await;
+    assert!(res.is_err_and(|e| e.is_panic()));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+use async_tasks_state_map::*;
+use tokio::sync::oneshot;
+
+use super::tools;
+
+pub async fn test_dependencies_success() {
+    let manager = AsyncTasksRecorder::new().with_concurrency_limit(1);
+    let mut task_id_generator = tools::get_task_id_generator();
+    let started = Arc::new(Mutex::new(Vec::new()));
+
+    let upload = task_id_generator();
+    let transcode = task_id_generator();
+
+    // waiting for a task not launched yet
+    let started_cloned = started.clone();
+    let transcode_cloned = transcode.clone();
+    assert!(manager.launch_with_options(transcode.clone(), async move {
+        started_cloned.lock().unwrap().push(transcode_cloned);
+        Ok::<(), ()>(())
+    }, LaunchOptions::new().with_prerequisites([upload.clone()])).await.is_ok());
+    assert_eq!(manager.query_task_state(&transcode).await, TaskState::Queued);
+    assert_eq!(manager.query_queue_len(), 0, "Task waiting for prerequisites should not take a slot");
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let started_cloned = started.clone();
+    let upload_cloned = upload.clone();
+    assert!(manager.launch(upload.clone(), async move {
+        started_cloned.lock().unwrap().push(upload_cloned);
+        let _ = rx.await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    assert_eq!(manager.query_task_state(&upload).await, TaskState::Working);
+    assert_eq!(manager.query_task_state(&transcode).await, TaskState::Queued);
+
+    tx.send(()).unwrap();
+    assert_eq!(manager.wait_for_finish(&transcode).await, TaskState::Success);
+    assert_eq!(*started.lock().unwrap(), vec![upload.clone(), transcode.clone()]);
+
+    // all prerequisites are already `Success`
+    let thumbnail = task_id_generator();
+    let res = manager.launch_block_with_options(thumbnail.clone(), async { Ok::<u32, ()>(1) }, LaunchOptions::new().with_prerequisites([upload, transcode])).await;
+    assert!(matches!(res, Ok(Some(Ok(1)))));
+}
+
+pub async fn test_dependencies_failure() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // a prerequisite fails
+    let upload = task_id_generator();
+    let transcode = task_id_generator();
+    let (tx, rx) = oneshot::channel::<()>();
+    assert!(manager.launch(upload.clone(), async move {
+        let _ = rx.await;
+        Err::<(), ()>(())
+    }).await.is_ok());
+    let manager_cloned = manager.clone();
+    let transcode_cloned = transcode.clone();
+    let upload_cloned = upload.clone();
+    let handle = tokio::spawn(async move {
+        manager_cloned.launch_block_with_options(transcode_cloned, async {
+            Ok::<(), ()>(())
+        }, LaunchOptions::new().with_prerequisites([upload_cloned])).await
+    });
+    assert_eq!(manager.wait_for_state(&transcode, &[TaskState::Queued]).await, TaskState::Queued);
+    tx.send(()).unwrap();
+    assert!(matches!(handle.await.unwrap(), Ok(None)), "Task should not run");
+    assert_eq!(manager.query_task_state(&transcode).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&transcode).await, Some(FailureReason::DependencyFailed));
+
+    // a prerequisite is revoked while waiting for another one
+    let first = task_id_generator();
+    let second = task_id_generator();
+    let dependent = task_id_generator();
+    assert!(manager.launch_block(first.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch_with_options(dependent.clone(), async {
+        Ok::<(), ()>(())
+    }, LaunchOptions::new().with_prerequisites([first.clone(), second.clone()])).await.is_ok());
+    assert!(manager.revoke_task_block(&first, async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch(second.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&dependent).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&dependent).await, Some(FailureReason::DependencyFailed));
+
+    // a later prerequisite fails while an earlier one is still working
+    let slow = task_id_generator();
+    let broken = task_id_generator();
+    let dependent = task_id_generator();
+    let (tx, rx) = oneshot::channel::<()>();
+    assert!(manager.launch(slow.clone(), async move {
+        let _ = rx.await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    assert!(manager.launch_with_options(dependent.clone(), async {
+        Ok::<(), ()>(())
+    }, LaunchOptions::new().with_prerequisites([slow.clone(), broken.clone()])).await.is_ok());
+    assert!(manager.launch_block(broken.clone(), async { Err::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&dependent).await, TaskState::Failed);
+    assert_eq!(manager.query_task_state(&slow).await, TaskState::Working,
+               "Failure should be seen without waiting for the others");
+    tx.send(()).unwrap();
+}
+
+pub async fn test_dependency_cycle() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+    let a = task_id_generator();
+    let b = task_id_generator();
+    let c = task_id_generator();
+
+    let (refusal, _) = manager.launch_with_options(a.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_prerequisites([a.clone()])).await.unwrap_err();
+    assert!(matches!(refusal, Refusal::DependencyCycle), "Task should not depend on itself");
+
+    // a -> b -> c -> a
+    assert!(manager.launch_with_options(a.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_prerequisites([b.clone()])).await.is_ok());
+    assert!(manager.launch_with_options(b.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_prerequisites([c.clone()])).await.is_ok());
+    let (refusal, _) = manager.launch_with_options(c.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_prerequisites([a.clone()])).await.unwrap_err();
+    assert!(matches!(refusal, Refusal::DependencyCycle));
+    assert_eq!(manager.query_task_state(&c).await, TaskState::NotFound, "Refused task should be left unchanged");
+
+    // no cycle after `a` stops waiting
+    assert!(manager.abort_task(&a).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&a).await, TaskState::Failed);
+    assert!(manager.launch_with_options(c.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_prerequisites([a.clone()])).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&c).await, TaskState::Failed);
+    assert_eq!(manager.wait_for_finish(&b).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&b).await, Some(FailureReason::DependencyFailed));
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+use async_tasks_state_map::*;
+use tokio_stream::StreamExt;
+
+use super::tools;
+
+async fn next_transition(stream: &mut TransitionStream<String>) -> (String, TaskState, TaskState, TransitionCause) {
+    let event = tokio::time::timeout(Duration::from_secs(1), stream.next()).await
+        .expect("Event should be received")
+        .unwrap()
+        .expect("Subscriber should not lag");
+    (event.task_id, event.old_state, event.new_state, event.cause)
+}
+
+pub async fn test_transition_events() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+    let task_id = task_id_generator();
+    let other_id = task_id_generator();
+
+    let mut all_stream = manager.subscribe_transitions(TransitionFilter::new());
+    let mut task_stream = manager.subscribe_transitions(TransitionFilter::new().with_task_id(task_id.clone()));
+    let mut failed_stream = manager.subscribe_transitions(TransitionFilter::new().with_new_states(&[TaskState::Failed]));
+
+    assert!(manager.launch_block(task_id.clone(), async { Err::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch_block(other_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&task_id, async { Err::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    manager.modify_state_force(other_id.clone(), TaskState::Failed).await;
+
+    let expected = [
+        (task_id.clone(), TaskState::NotFound, TaskState::Working, TransitionCause::Launched),
+        (task_id.clone(), TaskState::Working, TaskState::Failed, TransitionCause::Failed(FailureReason::Error)),
+        (other_id.clone(), TaskState::NotFound, TaskState::Working, TransitionCause::Launched),
+        (other_id.clone(), TaskState::Working, TaskState::Success, TransitionCause::Succeeded),
+        (task_id.clone(), TaskState::Failed, TaskState::Working, TransitionCause::Launched),
+        (task_id.clone(), TaskState::Working, TaskState::Success, TransitionCause::Succeeded),
+        (task_id.clone(), TaskState::Success, TaskState::Revoking, TransitionCause::RevokeStarted),
+        (task_id.clone(), TaskState::Revoking, TaskState::Success, TransitionCause::RevokeFailed),
+        (task_id.clone(), TaskState::Success, TaskState::Revoking, TransitionCause::RevokeStarted),
+        (task_id.clone(), TaskState::Revoking, TaskState::NotFound, TransitionCause::Revoked),
+        (other_id.clone(), TaskState::Success, TaskState::Failed, TransitionCause::Forced),
+    ];
+    for transition in expected.iter() {
+        assert_eq!(next_transition(&mut all_stream).await, *transition);
+    }
+    for transition in expected.iter().filter(|t| t.0 == task_id) {
+        assert_eq!(next_transition(&mut task_stream).await, *transition);
+    }
+    for transition in expected.iter().filter(|t| t.2 == TaskState::Failed) {
+        assert_eq!(next_transition(&mut failed_stream).await, *transition);
+    }
+
+    // the stream ends when the recorder is dropped
+    drop(manager);
+    assert!(all_stream.next().await.is_none());
+}
+
+pub async fn test_transition_events_lagged() {
+    let manager = AsyncTasksRecorder::new().with_event_capacity(4);
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut stream = manager.subscribe_transitions(TransitionFilter::new());
+    for _ in 0..5 {
+        assert!(manager.launch_block(task_id_generator(), async { Ok::<(), ()>(()) }).await.is_ok());
+    }
+
+    // 10 events are sent, and the oldest 6 are dropped
+    assert_eq!(stream.next().await, Some(Err(EventsLagged(6))));
+    for _ in 0..4 {
+        assert!(matches!(stream.next().await, Some(Ok(_))));
+    }
+    assert!(manager.launch_block(task_id_generator(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(stream.next().await.unwrap().unwrap().new_state, TaskState::Working);
+}
+
+pub async fn test_transition_events_ordered(worker_num: usize, round_num: usize) {
+    let manager = AsyncTasksRecorder::new().with_event_capacity(1 << 16);
+    let mut task_id_generator = tools::get_task_id_generator();
+    let task_id = task_id_generator();
+    let mut stream = manager.subscribe_transitions(TransitionFilter::new());
+
+    // change the same task concurrently
+    let mut join_set = tokio::task::JoinSet::new();
+    for worker in 0..worker_num {
+        let manager = manager.clone();
+        let task_id = task_id.clone();
+        join_set.spawn(async move {
+            for round in 0..round_num {
+                match (worker + round) % 3 {
+                    0 => {
+                        let _ = manager.launch(task_id.clone(), async { Ok::<(), ()>(()) }).await;
+                    }
+                    1 => {
+                        let _ = manager.revoke_task(&task_id, async { Ok::<(), ()>(()) }).await;
+                    }
+                    _ => manager.modify_state_force(task_id.clone(), TaskState::Failed).await,
+                }
+                tokio::task::yield_now().await;
+            }
+        });
+    }
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+
+    // each event starts from where the previous one ends
+    let mut state = TaskState::NotFound;
+    let mut event_num = 0;
+    while let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(100), stream.next()).await {
+        let event = event.expect("Subscriber should not lag");
+        assert_eq!(event.old_state, state, "Events out of order at {}: {:?}", event_num, event);
+        state = event.new_state;
+        event_num += 1;
+    }
+    assert!(event_num > 0);
+    assert_eq!(state, manager.query_task_state(&task_id).await);
+}
@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_expiry_lazy_and_sweep() {
+    let expired = Arc::new(Mutex::new(Vec::new()));
+    let expired_cloned = expired.clone();
+    let manager = AsyncTasksRecorder::new()
+        .with_success_ttl(Duration::from_millis(50))
+        .with_failed_ttl(Duration::from_millis(150))
+        .with_expire_hook(move |task_id: &String, state| {
+            expired_cloned.lock().unwrap().push((task_id.clone(), state));
+        });
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // expire lazily when queried
+    let success_id = task_id_generator();
+    assert!(manager.launch_block(success_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    let failed_id = task_id_generator();
+    assert!(manager.launch_block(failed_id.clone(), async { Err::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&success_id).await, TaskState::Success);
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(manager.query_task_state(&success_id).await, TaskState::NotFound,
+               "Expired task should be NotFound {}", success_id);
+    assert_eq!(manager.query_task_state(&failed_id).await, TaskState::Failed);
+    assert!(!manager.get_recorder_ref().contains(&success_id));
+    assert_eq!(*expired.lock().unwrap(), vec![(success_id.clone(), TaskState::Success)]);
+
+    // expire by sweeping
+    assert_eq!(manager.sweep_expired().await, vec![]);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(manager.sweep_expired().await, vec![(failed_id.clone(), TaskState::Failed)]);
+    assert_eq!(manager.get_recorder_ref().len(), 0);
+    assert_eq!(expired.lock().unwrap().len(), 2);
+
+    // launch an expired task again
+    assert!(manager.launch_block(success_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert!(manager.launch_block(success_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok(),
+            "Expired task should be able to launch again {}", success_id);
+    assert_eq!(expired.lock().unwrap().len(), 3);
+
+    // revoking is not expiry, and unsuccessful revoking restarts TTL
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(manager.revoke_task_block(&success_id, async { Err::<(), ()>(()) }).await.is_ok());
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(manager.query_task_state(&success_id).await, TaskState::Success);
+    assert!(manager.revoke_task_block(&success_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(manager.sweep_expired().await, vec![]);
+    assert_eq!(expired.lock().unwrap().len(), 3);
+}
+
+pub async fn test_expiry_sweeper(task_num: usize) {
+    let manager = AsyncTasksRecorder::new()
+        .with_success_ttl(Duration::from_millis(20))
+        .with_failed_ttl(Duration::from_millis(20));
+    let mut task_id_generator = tools::get_task_id_generator();
+    let sweeper = manager.spawn_sweeper(Duration::from_millis(10));
+
+    for i in 0..task_num {
+        let task = async move {
+            tokio::time::sleep(Duration::from_millis(fastrand::u64(1..30))).await;
+            if i % 2 == 0 { Ok(()) } else { Err(()) }
+        };
+        assert!(manager.launch(task_id_generator(), task).await.is_ok());
+    }
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(manager.get_recorder_ref().len(), 0,
+               "All the finished tasks should be swept");
+    sweeper.abort();
+}
@@ -0,0 +1,95 @@
+use async_tasks_state_map::*;
+use tokio::sync::oneshot;
+
+type TaskId = (u32, u32);
+
+/// Launch a task which does not finish until the returned sender is used or dropped.
+async fn launch_gated(manager: &AsyncTasksRecorder<TaskId>, task_id: TaskId) -> Result<oneshot::Sender<()>, Refusal> {
+    let (tx, rx) = oneshot::channel::<()>();
+    manager.launch(task_id, async move {
+        let _ = rx.await;
+        Ok::<(), ()>(())
+    }).await.map_err(|(refusal, _)| refusal)?;
+    Ok(tx)
+}
+
+pub async fn test_group_limits_reject() {
+    let manager = AsyncTasksRecorder::new().with_group_limits(
+        |task_id: &TaskId| task_id.0,
+        |_| GroupLimits {
+            max_working: Some(1),
+            max_entries: Some(2),
+            ..Default::default()
+        },
+    );
+
+    let gate = launch_gated(&manager, (1, 0)).await.unwrap();
+    let refusal = launch_gated(&manager, (1, 1)).await.unwrap_err();
+    assert_eq!(refusal.quota(), Some(Quota::Working));
+    assert_eq!(manager.query_task_state(&(1, 1)).await, TaskState::NotFound, "Refused task should be left unchanged");
+    // other groups are not affected
+    let other_gate = launch_gated(&manager, (2, 0)).await.unwrap();
+
+    drop(gate);
+    assert_eq!(manager.wait_for_finish(&(1, 0)).await, TaskState::Success);
+    assert!(manager.launch((1, 1), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&(1, 1)).await, TaskState::Success);
+
+    // the group has 2 entries
+    let refusal = launch_gated(&manager, (1, 2)).await.unwrap_err();
+    assert_eq!(refusal.quota(), Some(Quota::Entries));
+    assert_eq!(manager.query_task_state(&(1, 2)).await, TaskState::NotFound);
+
+    // a failed task can be relaunched, since it is already recorded
+    manager.modify_state_force((1, 1), TaskState::Failed).await;
+    assert!(manager.launch((1, 1), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&(1, 1)).await, TaskState::Success);
+
+    // revoking frees an entry
+    assert!(manager.revoke_task_block(&(1, 0), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch((1, 2), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&(1, 2)).await, TaskState::Success);
+
+    drop(other_gate);
+    assert_eq!(manager.wait_for_finish(&(2, 0)).await, TaskState::Success);
+}
+
+pub async fn test_group_limits_queue() {
+    let manager = AsyncTasksRecorder::new()
+        .with_concurrency_limit(3)
+        .with_group_limits(
+            |task_id: &TaskId| task_id.0,
+            |group| GroupLimits {
+                max_working: if *group == 1 { Some(1) } else { None },
+                when_busy: WhenBusy::Queue,
+                ..Default::default()
+            },
+        );
+
+    let gate_1 = launch_gated(&manager, (1, 0)).await.unwrap();
+    let gate_2 = launch_gated(&manager, (2, 0)).await.unwrap();
+    let _gate_1_queued = launch_gated(&manager, (1, 1)).await.unwrap();
+    assert_eq!(manager.query_task_state(&(1, 1)).await, TaskState::Queued, "Group should be full");
+    let gate_3 = launch_gated(&manager, (3, 0)).await.unwrap();
+    assert_eq!(manager.query_task_state(&(3, 0)).await, TaskState::Working);
+
+    // the concurrency limit is reached
+    let _gate_2_queued = launch_gated(&manager, (2, 1)).await.unwrap();
+    let _gate_3_queued = launch_gated(&manager, (3, 1)).await.unwrap();
+    assert_eq!(manager.query_queue_len(), 3);
+
+    // group 3 has fewer `Working` tasks than group 2 after group 3 finishes one
+    drop(gate_3);
+    assert_eq!(manager.wait_for_state(&(3, 1), &[TaskState::Working]).await, TaskState::Working);
+    assert_eq!(manager.query_task_state(&(2, 1)).await, TaskState::Queued);
+    assert_eq!(manager.query_task_state(&(1, 1)).await, TaskState::Queued, "Group should still be full");
+
+    // group 1 is no longer full, and has fewer `Working` tasks than group 2
+    drop(gate_1);
+    assert_eq!(manager.wait_for_state(&(1, 1), &[TaskState::Working]).await, TaskState::Working);
+    assert_eq!(manager.query_task_state(&(2, 1)).await, TaskState::Queued);
+
+    drop(gate_2);
+    assert_eq!(manager.wait_for_state(&(2, 1), &[TaskState::Working]).await, TaskState::Working);
+    assert_eq!(manager.query_queue_len(), 0);
+}
@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+#[derive(Debug, PartialEq)]
+struct QuotaExceeded;
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "quota exceeded")
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+#[derive(Debug, PartialEq)]
+struct LegalHold(String);
+
+impl std::fmt::Display for LegalHold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is under legal hold", self.0)
+    }
+}
+
+impl std::error::Error for LegalHold {}
+
+struct QuotaGuard {
+    checked: Arc<AtomicUsize>,
+    quota: usize,
+}
+
+impl TransitionGuard<String> for QuotaGuard {
+    fn check_launch<'a>(&'a self, _task_id: &'a String, _state: &'a TaskState) -> GuardFuture<'a> {
+        Box::pin(async move {
+            // take some time, during which the state should not be changed by others
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            if self.checked.fetch_add(1, Ordering::SeqCst) >= self.quota {
+                return Err(Rejection::new(QuotaExceeded));
+            }
+            Ok(())
+        })
+    }
+}
+
+struct LegalHoldGuard {
+    holds: Arc<Mutex<HashSet<String>>>,
+}
+
+impl TransitionGuard<String> for LegalHoldGuard {
+    fn check_revoke<'a>(&'a self, task_id: &'a String) -> GuardFuture<'a> {
+        Box::pin(async move {
+            if self.holds.lock().unwrap().contains(task_id) {
+                return Err(Rejection::new(LegalHold(task_id.clone())));
+            }
+            Ok(())
+        })
+    }
+}
+
+pub async fn test_guards() {
+    let holds = Arc::new(Mutex::new(HashSet::new()));
+    let checked = Arc::new(AtomicUsize::new(0));
+    let manager = AsyncTasksRecorder::new()
+        .with_guard(QuotaGuard { checked: checked.clone(), quota: 3 })
+        .with_guard(LegalHoldGuard { holds: holds.clone() });
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // checked atomically with the state
+    let task_id = task_id_generator();
+    let manager_cloned = manager.clone();
+    let task_id_cloned = task_id.clone();
+    let other_launch = tokio::spawn(async move {
+        manager_cloned.launch_block(task_id_cloned, async { Ok::<(), ()>(()) }).await
+            .map_err(|(refusal, _)| refusal)
+    });
+    let res = manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await
+        .map_err(|(refusal, _)| refusal);
+    let refusal = match (res, other_launch.await.unwrap()) {
+        (Ok(_), Err(refusal)) | (Err(refusal), Ok(_)) => refusal,
+        _ => panic!("Only one launch should success {}", task_id),
+    };
+    assert!(matches!(refusal.state(), Some(TaskState::Working) | Some(TaskState::Success)),
+            "Unexpected refusal {:?}", refusal);
+    assert_eq!(checked.load(Ordering::SeqCst), 1);
+
+    // reject revoking
+    holds.lock().unwrap().insert(task_id.clone());
+    let (refusal, _) = manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.unwrap_err();
+    assert_eq!(refusal.rejection().unwrap().downcast_ref::<LegalHold>(), Some(&LegalHold(task_id.clone())));
+    let (refusal, _) = manager.revoke_task(&task_id, async { Ok::<(), ()>(()) }).await.unwrap_err();
+    assert!(refusal.rejection().is_some());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success,
+               "Rejected revoking should leave the state unchanged {}", task_id);
+    holds.lock().unwrap().clear();
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+
+    // reject launching
+    assert!(manager.launch(task_id_generator(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch_block(task_id_generator(), async { Err::<(), ()>(()) }).await.is_ok());
+    let task_id = task_id_generator();
+    let (refusal, _) = manager.launch(task_id.clone(), async { Ok::<(), ()>(()) }).await.unwrap_err();
+    assert_eq!(refusal.rejection().unwrap().downcast_ref::<QuotaExceeded>(), Some(&QuotaExceeded));
+    assert_eq!(refusal.rejection().unwrap().to_string(), "quota exceeded");
+    let (refusal, _) = manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.unwrap_err();
+    assert!(refusal.rejection().is_some());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound,
+               "Rejected launch should leave the state unchanged {}", task_id);
+}
+
+struct QueryingGuard {
+    recorder: Arc<OnceLock<AsyncTasksRecorder<String>>>,
+    seen: Arc<Mutex<Vec<TaskState>>>,
+}
+
+impl TransitionGuard<String> for QueryingGuard {
+    fn check_launch<'a>(&'a self, task_id: &'a String, _state: &'a TaskState) -> GuardFuture<'a> {
+        Box::pin(async move {
+            // the entry is not locked while checking
+            let state = self.recorder.get().unwrap().query_task_state(task_id).await;
+            self.seen.lock().unwrap().push(state);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(())
+        })
+    }
+
+    fn check_revoke<'a>(&'a self, task_id: &'a String) -> GuardFuture<'a> {
+        Box::pin(async move {
+            let state = self.recorder.get().unwrap().query_task_state(task_id).await;
+            self.seen.lock().unwrap().push(state);
+            Ok(())
+        })
+    }
+}
+
+pub async fn test_guards_query_recorder() {
+    let recorder = Arc::new(OnceLock::new());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let manager = AsyncTasksRecorder::new()
+        .with_guard(QueryingGuard { recorder: recorder.clone(), seen: seen.clone() });
+    recorder.set(manager.clone()).unwrap();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // the guards can query the recorder
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(*seen.lock().unwrap(), vec![TaskState::NotFound, TaskState::Success]);
+
+    // the state changed during checking is checked again
+    let task_id = task_id_generator();
+    let manager_cloned = manager.clone();
+    let task_id_cloned = task_id.clone();
+    let modification = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        manager_cloned.modify_state_force(task_id_cloned, TaskState::Success).await;
+    });
+    let (refusal, _) = manager.launch(task_id.clone(), async { Ok::<(), ()>(()) }).await.unwrap_err();
+    modification.await.unwrap();
+    assert_eq!(refusal.state(), Some(&TaskState::Success));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+}
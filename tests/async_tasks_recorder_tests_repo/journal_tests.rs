@@ -0,0 +1,108 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+fn get_journal_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("async_tasks_journal_{}_{}.jsonl", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+pub async fn test_journal_replay() {
+    let path = get_journal_path("replay");
+    let manager = AsyncTasksRecorder::<String>::open_journal(&path, &RestoreOptions::default()).unwrap();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let success_id = task_id_generator();
+    assert!(manager.launch_block(success_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    let failed_id = task_id_generator();
+    assert!(manager.launch_block(failed_id.clone(), async { Err::<(), ()>(()) }).await.is_ok());
+    let working_id = task_id_generator();
+    assert!(manager.launch(working_id.clone(), async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    let revoked_id = task_id_generator();
+    assert!(manager.launch_block(revoked_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&revoked_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    let forced_id = task_id_generator();
+    manager.modify_state_force(forced_id.clone(), TaskState::Success).await;
+    manager.sync_journal().await.unwrap();
+
+    // replay as if restarted
+    let replayed = AsyncTasksRecorder::<String>::open_journal(&path, &RestoreOptions::default()).unwrap();
+    assert_eq!(replayed.get_recorder_ref().len(), 4);
+    assert_eq!(replayed.query_task_state(&success_id).await, TaskState::Success);
+    assert_eq!(replayed.query_task_state(&failed_id).await, TaskState::Failed);
+    assert_eq!(replayed.query_task_state(&working_id).await, TaskState::Failed,
+               "Working task should be mapped to Failed {}", working_id);
+    assert_eq!(replayed.query_task_state(&revoked_id).await, TaskState::NotFound);
+    assert_eq!(replayed.query_task_state(&forced_id).await, TaskState::Success);
+    assert!(replayed.revoke_task_block(&success_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    drop(replayed);
+
+    // tolerate a torn final record
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"[\"torn\",\"Succ").unwrap();
+    drop(file);
+    let replayed = AsyncTasksRecorder::<String>::open_journal(&path, &RestoreOptions::default()).unwrap();
+    assert_eq!(replayed.get_recorder_ref().len(), 3);
+    assert_eq!(replayed.query_task_state(&success_id).await, TaskState::NotFound);
+    assert_eq!(replayed.query_task_state("torn").await, TaskState::NotFound);
+    assert!(replayed.launch_block(failed_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    drop(replayed);
+
+    // refuse a broken record which is not the last one
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"[\"broken\"]\n[\"fine\",\"Success\"]\n").unwrap();
+    drop(file);
+    assert!(AsyncTasksRecorder::<String>::open_journal(&path, &RestoreOptions::default()).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+pub async fn test_journal_compaction(task_num: usize, redo_num: usize) {
+    let path = get_journal_path("compaction");
+    let manager = AsyncTasksRecorder::<String>::open_journal(&path, &RestoreOptions::default()).unwrap();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for _ in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        join_set.spawn(async move {
+            for _ in 0..redo_num {
+                assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+                assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+            }
+            assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+            if fastrand::bool() {
+                manager.compact_journal().await.unwrap();
+            }
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+
+    manager.compact_journal().await.unwrap();
+    manager.sync_journal().await.unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(content.lines().count(), task_num);
+
+    let replayed = AsyncTasksRecorder::<String>::open_journal(&path, &RestoreOptions::default()).unwrap();
+    assert_eq!(replayed.get_recorder_ref().len(), task_num);
+    let mut all_success = true;
+    replayed.get_recorder_ref().scan(|_, v| all_success &= *v == TaskState::Success);
+    assert!(all_success);
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,174 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_concurrency_limit_fifo() {
+    let manager = AsyncTasksRecorder::new().with_concurrency_limit(2);
+    let mut task_id_generator = tools::get_task_id_generator();
+    let started = Arc::new(Mutex::new(Vec::new()));
+
+    let task_ids: Vec<String> = (0..6).map(|_| task_id_generator()).collect();
+    for task_id in task_ids.iter() {
+        let started = started.clone();
+        let task_id_cloned = task_id.clone();
+        assert!(manager.launch(task_id.clone(), async move {
+            started.lock().unwrap().push(task_id_cloned);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<(), ()>(())
+        }).await.is_ok());
+    }
+    assert_eq!(manager.query_task_state(&task_ids[1]).await, TaskState::Working);
+    assert_eq!(manager.query_task_state(&task_ids[2]).await, TaskState::Queued);
+    assert_eq!(manager.query_queue_len(), 4);
+    let (refusal, _) = manager.launch(task_ids[5].clone(), async { Ok::<(), ()>(()) }).await.unwrap_err();
+    assert_eq!(refusal.state(), Some(&TaskState::Queued));
+
+    // abort a queued task
+    assert!(manager.abort_task(&task_ids[3]).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_ids[3]).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&task_ids[3]).await, Some(FailureReason::Aborted));
+    assert_eq!(manager.query_queue_len(), 3);
+
+    for task_id in task_ids.iter() {
+        manager.wait_for_finish(task_id).await;
+    }
+    let expected: Vec<String> = task_ids.iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 3)
+        .map(|(_, task_id)| task_id.clone())
+        .collect();
+    assert_eq!(*started.lock().unwrap(), expected, "Queued tasks should start in FIFO order");
+    assert_eq!(manager.query_queue_len(), 0);
+
+    // timed out in queue
+    for _ in 0..2 {
+        assert!(manager.launch(task_id_generator(), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<(), ()>(())
+        }).await.is_ok());
+    }
+    let task_id = task_id_generator();
+    let res = manager.launch_block_with_options(task_id.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_timeout(Duration::from_millis(10))).await;
+    assert!(matches!(res, Ok(None)), "Queued task should be timed out {}", task_id);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::TimedOut));
+    assert_eq!(manager.query_queue_len(), 0);
+}
+
+pub async fn test_concurrency_limit(task_num: usize, limit: usize) {
+    let manager = AsyncTasksRecorder::new().with_concurrency_limit(limit);
+    let mut task_id_generator = tools::get_task_id_generator();
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for _ in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        let running = running.clone();
+        let max_running = max_running.clone();
+        join_set.spawn(async move {
+            let res = manager.launch(task_id.clone(), async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_micros(fastrand::u64(1..500))).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok::<(), ()>(())
+            }).await;
+            assert!(res.is_ok(), "Launch should success {}", task_id);
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success,
+                       "Task should success {}", task_id);
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+
+    assert!(max_running.load(Ordering::SeqCst) <= limit,
+            "Too many running tasks: {}", max_running.load(Ordering::SeqCst));
+    assert_eq!(manager.query_queue_len(), 0);
+}
+
+pub async fn test_priority_admission_order() {
+    let manager = AsyncTasksRecorder::new().with_concurrency_limit(1);
+    let mut task_id_generator = tools::get_task_id_generator();
+    let started = Arc::new(Mutex::new(Vec::new()));
+
+    // occupy the only slot until all the others are queued
+    let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+    let blocker = task_id_generator();
+    assert!(manager.launch(blocker.clone(), async move {
+        let _ = release_rx.await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+
+    let priorities = [0, 5, 1, 5, -3];
+    let mut task_ids = Vec::new();
+    for priority in priorities {
+        let started = started.clone();
+        let task_id = task_id_generator();
+        let task_id_cloned = task_id.clone();
+        assert!(manager.launch_with_options(task_id.clone(), async move {
+            started.lock().unwrap().push(task_id_cloned);
+            Ok::<(), ()>(())
+        }, LaunchOptions::new().with_priority(priority)).await.is_ok());
+        assert_eq!(manager.query_task_state(&task_id).await, TaskState::Queued);
+        task_ids.push(task_id);
+    }
+    assert_eq!(manager.query_queue_len(), priorities.len());
+
+    release_tx.send(()).unwrap();
+    for task_id in task_ids.iter() {
+        assert_eq!(manager.wait_for_finish(task_id).await, TaskState::Success);
+    }
+    let expected: Vec<String> = [1, 3, 2, 0, 4].iter().map(|i| task_ids[*i].clone()).collect();
+    assert_eq!(*started.lock().unwrap(), expected,
+               "Queued tasks should start by priority, and FIFO for the same priority");
+}
+
+pub async fn test_priority_aging(aging_first: bool) {
+    let manager = match aging_first {
+        true => AsyncTasksRecorder::new()
+            .with_priority_aging(Duration::from_millis(10))
+            .with_concurrency_limit(1),
+        false => AsyncTasksRecorder::new()
+            .with_concurrency_limit(1)
+            .with_priority_aging(Duration::from_millis(10)),
+    };
+    let mut task_id_generator = tools::get_task_id_generator();
+    let started = Arc::new(Mutex::new(Vec::new()));
+
+    let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+    assert!(manager.launch(task_id_generator(), async move {
+        let _ = release_rx.await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+
+    // the old task with low priority has waited long enough to overtake
+    let old_task_id = task_id_generator();
+    let new_task_id = task_id_generator();
+    for (task_id, priority) in [(&old_task_id, 0), (&new_task_id, 3)] {
+        let started = started.clone();
+        let task_id_cloned = task_id.clone();
+        assert!(manager.launch_with_options(task_id.clone(), async move {
+            started.lock().unwrap().push(task_id_cloned);
+            Ok::<(), ()>(())
+        }, LaunchOptions::new().with_priority(priority)).await.is_ok());
+        if priority == 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    release_tx.send(()).unwrap();
+    manager.wait_for_finish(&old_task_id).await;
+    manager.wait_for_finish(&new_task_id).await;
+    assert_eq!(*started.lock().unwrap(), vec![old_task_id, new_task_id],
+               "Aging should let the old task start first");
+}
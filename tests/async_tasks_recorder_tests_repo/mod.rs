@@ -1,8 +1,60 @@
 use async_tasks_state_map::*;
 
 mod tools;
+mod result_tests;
+mod wait_tests;
+mod abort_tests;
+mod compensation_tests;
+mod panic_tests;
+mod timeout_tests;
+mod retry_tests;
+mod revoke_failure_tests;
+mod revoke_working_tests;
+mod expiry_tests;
+mod runtime_handle_tests;
+mod events_tests;
+mod observer_tests;
+mod guard_tests;
+mod limit_tests;
+mod group_tests;
+mod dependency_tests;
+mod progress_tests;
+mod snapshot_tests;
+mod task_handle_tests;
+mod transition_tests;
+#[cfg(feature = "serde")]
+mod journal_tests;
+#[cfg(feature = "runtime-smol")]
+mod spawner_tests;
 
 pub use tools::{RuntimeType, do_async_test};
+#[cfg(feature = "runtime-smol")]
+pub use tools::do_smol_test;
+pub use result_tests::*;
+pub use wait_tests::*;
+pub use abort_tests::*;
+pub use compensation_tests::*;
+pub use panic_tests::*;
+pub use timeout_tests::*;
+pub use retry_tests::*;
+pub use revoke_failure_tests::*;
+pub use revoke_working_tests::*;
+pub use expiry_tests::*;
+pub use runtime_handle_tests::*;
+pub use events_tests::*;
+pub use observer_tests::*;
+pub use guard_tests::*;
+pub use limit_tests::*;
+pub use group_tests::*;
+pub use dependency_tests::*;
+pub use progress_tests::*;
+pub use snapshot_tests::*;
+pub use task_handle_tests::*;
+pub use transition_tests::*;
+#[cfg(feature = "serde")]
+pub use journal_tests::*;
+#[cfg(feature = "runtime-smol")]
+pub use spawner_tests::*;
 
 pub async fn test_simple_launch_check(task_num: usize) {
     let manager = AsyncTasksRecorder::new();
@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+#[derive(Default)]
+struct LogObserver {
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl TransitionObserver<String> for LogObserver {
+    fn on_launch(&self, task_id: &String, old_state: &TaskState) {
+        self.log.lock().unwrap().push(format!("launch {} {:?}", task_id, old_state));
+    }
+
+    fn on_success(&self, task_id: &String) {
+        self.log.lock().unwrap().push(format!("success {}", task_id));
+    }
+
+    fn on_failure(&self, task_id: &String, reason: &FailureReason) {
+        self.log.lock().unwrap().push(format!("failure {} {:?}", task_id, reason));
+    }
+
+    fn on_revoke_start(&self, task_id: &String) {
+        self.log.lock().unwrap().push(format!("revoke_start {}", task_id));
+    }
+
+    fn on_revoked(&self, task_id: &String) {
+        self.log.lock().unwrap().push(format!("revoked {}", task_id));
+    }
+
+    fn on_revoke_failed(&self, task_id: &String) {
+        self.log.lock().unwrap().push(format!("revoke_failed {}", task_id));
+    }
+}
+
+#[derive(Default)]
+struct CountObserver {
+    working: Arc<AtomicUsize>,
+    success: Arc<AtomicUsize>,
+}
+
+impl TransitionObserver<String> for CountObserver {
+    fn on_launch(&self, _task_id: &String, _old_state: &TaskState) {
+        self.working.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_success(&self, _task_id: &String) {
+        self.working.fetch_sub(1, Ordering::SeqCst);
+        self.success.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+pub async fn test_observer_calls() {
+    let observer = LogObserver::default();
+    let log = observer.log.clone();
+    let manager = AsyncTasksRecorder::new()
+        .with_observer(observer);
+    let mut task_id_generator = tools::get_task_id_generator();
+    let task_id = task_id_generator();
+
+    assert!(manager.launch_block(task_id.clone(), async { Err::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&task_id, async { Err::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.launch(task_id.clone(), async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    assert!(manager.abort_task(&task_id).await.is_ok());
+
+    // not observed
+    manager.modify_state_force(task_id.clone(), TaskState::NotFound).await;
+
+    let expected: Vec<String> = [
+        "launch {} NotFound",
+        "failure {} Error",
+        "launch {} Failed",
+        "success {}",
+        "revoke_start {}",
+        "revoke_failed {}",
+        "revoke_start {}",
+        "revoked {}",
+        "launch {} NotFound",
+        "failure {} Aborted",
+    ].iter().map(|s| s.replace("{}", &task_id)).collect();
+    assert_eq!(*log.lock().unwrap(), expected);
+}
+
+pub async fn test_observer_counts(task_num: usize) {
+    let observer = CountObserver::default();
+    let working = observer.working.clone();
+    let success = observer.success.clone();
+    let manager = AsyncTasksRecorder::new()
+        .with_observer(observer);
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for _ in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        join_set.spawn(async move {
+            assert!(manager.launch(task_id.clone(), async {
+                tokio::time::sleep(Duration::from_millis(fastrand::u64(1..20))).await;
+                Ok::<(), ()>(())
+            }).await.is_ok());
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+
+    // observers are called before the waiters are woken up
+    assert_eq!(working.load(Ordering::SeqCst), 0);
+    assert_eq!(success.load(Ordering::SeqCst), task_num);
+}
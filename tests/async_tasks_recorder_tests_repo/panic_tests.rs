@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_panic_launch_revoke() {
+    let (panic_tx, mut panic_rx) = tokio::sync::mpsc::unbounded_channel();
+    let manager = AsyncTasksRecorder::new()
+        .with_panic_hook(move |task_id: &String, payload| {
+            let msg = payload.downcast_ref::<&str>().copied().unwrap_or_default();
+            panic_tx.send((task_id.clone(), msg)).unwrap();
+        });
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // panicking task launched by `launch`
+    let task_id = task_id_generator();
+    let task = async {
+        tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+        if true {
+            panic!("launch panic");
+        }
+        Ok::<(), ()>(())
+    };
+    assert!(manager.launch(task_id.clone(), task).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Failed,
+               "Panicking task should be Failed {}", task_id);
+    assert_eq!(panic_rx.recv().await, Some((task_id.clone(), "launch panic")));
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Panicked));
+
+    // relaunch, then panicking revoking launched by `revoke_task`
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    let revoke_task = async {
+        if true {
+            panic!("revoke panic");
+        }
+        Ok::<(), ()>(())
+    };
+    assert!(manager.revoke_task(&task_id, revoke_task).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success,
+               "Panicking revoking should make task Success {}", task_id);
+    assert_eq!(panic_rx.recv().await, Some((task_id.clone(), "revoke panic")));
+
+    // panicking task launched by `launch_block`
+    let task_id = task_id_generator();
+    let blocked = {
+        let manager = manager.clone();
+        let task_id = task_id.clone();
+        tokio::spawn(async move {
+            let task = async {
+                if true {
+                    panic!("launch_block panic");
+                }
+                Ok::<(), ()>(())
+            };
+            let _ = manager.launch_block(task_id, task).await;
+        })
+    };
+    let payload = blocked.await.unwrap_err().into_panic();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"launch_block panic"),
+               "`launch_block` should resume the panic {}", task_id);
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+    assert!(panic_rx.try_recv().is_err(), "Panic of `launch_block` shouldn't be passed to the hook");
+}
+
+pub async fn test_drop_launch_block() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // `launch_block` is cancelled by timeout
+    let task_id = task_id_generator();
+    let task = async {
+        tokio::time::sleep(tokio::time::Duration::from_secs(100)).await;
+        Ok::<(), ()>(())
+    };
+    let res = tokio::time::timeout(
+        tokio::time::Duration::from_millis(10),
+        manager.launch_block(task_id.clone(), task)).await;
+    assert!(res.is_err());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed,
+               "Dropped task should be Failed {}", task_id);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Dropped));
+    assert_eq!(manager.abort_task(&task_id).await, Err(TaskState::Failed));
+
+    // the `tokio` task executing `revoke_task_block` is aborted
+    let task_id = Arc::new(task_id);
+    let manager = AsyncTasksRecorder::new();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    let blocked = {
+        let manager = manager.clone();
+        let task_id = task_id.clone();
+        tokio::spawn(async move {
+            let revoke_task = async {
+                tokio::time::sleep(tokio::time::Duration::from_secs(100)).await;
+                Ok::<(), ()>(())
+            };
+            let _ = manager.revoke_task_block(&task_id, revoke_task).await;
+        })
+    };
+    assert_eq!(manager.wait_for_state(&task_id, &[TaskState::Revoking]).await, TaskState::Revoking);
+    blocked.abort();
+    assert!(blocked.await.unwrap_err().is_cancelled());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success,
+               "Dropped revoking should make task Success {}", task_id);
+}
@@ -0,0 +1,85 @@
+use async_tasks_state_map::*;
+use tokio::sync::oneshot;
+
+use super::tools;
+
+pub async fn test_progress() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+    let task_id = task_id_generator();
+
+    let (step_tx, step_rx) = oneshot::channel::<()>();
+    let (finish_tx, finish_rx) = oneshot::channel::<()>();
+    assert!(manager.launch_with_progress(task_id.clone(), |reporter| async move {
+        reporter.report(Progress::Bytes { done: 512, total: Some(1024) });
+        let _ = step_rx.await;
+        reporter.report(Progress::Fraction(1.0));
+        let _ = finish_rx.await;
+        Ok::<(), ()>(())
+    }, LaunchOptions::new()).await.is_ok());
+    let mut progress_rx = manager.subscribe_progress(&task_id).unwrap();
+
+    let progress = progress_rx.wait_for(Option::is_some).await.unwrap().clone();
+    assert_eq!(progress, Some(Progress::Bytes { done: 512, total: Some(1024) }));
+    assert_eq!(manager.query_progress(&task_id), progress);
+
+    step_tx.send(()).unwrap();
+    let progress = progress_rx.wait_for(|progress| *progress == Some(Progress::Fraction(1.0))).await.unwrap().clone();
+    assert_eq!(manager.query_progress(&task_id), progress);
+
+    // cleared on completion
+    finish_tx.send(()).unwrap();
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+    assert_eq!(manager.query_progress(&task_id), None);
+    assert!(manager.subscribe_progress(&task_id).is_none());
+    assert!(progress_rx.changed().await.is_ok());
+    assert_eq!(*progress_rx.borrow_and_update(), None);
+    assert!(progress_rx.changed().await.is_err(), "Channel should be closed after the task finishes");
+
+    // custom payload, with a failing task
+    let task_id = task_id_generator();
+    let res = manager.launch_block_with_progress(task_id.clone(), |reporter| async move {
+        reporter.report(Progress::Custom(ProgressPayload::new("transcoding")));
+        Err::<(), ()>(())
+    }, LaunchOptions::new()).await;
+    assert!(matches!(res, Ok(Some(Err(())))));
+    assert_eq!(manager.query_progress(&task_id), None);
+
+    let task_id = task_id_generator();
+    let (tx, rx) = oneshot::channel::<()>();
+    assert!(manager.launch_with_progress(task_id.clone(), |reporter| async move {
+        reporter.report(Progress::Custom(ProgressPayload::new("transcoding")));
+        let _ = rx.await;
+        Ok::<(), ()>(())
+    }, LaunchOptions::new()).await.is_ok());
+    let mut progress_rx = manager.subscribe_progress(&task_id).unwrap();
+    let progress = progress_rx.wait_for(Option::is_some).await.unwrap().clone();
+    let Some(Progress::Custom(payload)) = progress else {
+        panic!("Progress should be custom, but got {:?}", progress);
+    };
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"transcoding"));
+
+    // refused launch does not create the task
+    let res = manager.launch_with_progress(task_id.clone(), |_| async { Ok::<(), ()>(()) }, LaunchOptions::new()).await;
+    assert!(matches!(res, Err((Refusal::State(TaskState::Working), _))));
+
+    // cleared when aborted
+    assert!(manager.abort_task(&task_id).await.is_ok());
+    assert_eq!(manager.query_progress(&task_id), None);
+    drop(tx);
+
+    // a panicking closure fails the task
+    let task_id = task_id_generator();
+    let manager_cloned = manager.clone();
+    let task_id_cloned = task_id.clone();
+    let res = tokio::spawn(async move {
+        manager_cloned.launch_block_with_progress(task_id_cloned, |_| -> std::future::Ready<Result<(), ()>> {
+            panic!("no reporter");
+        }, LaunchOptions::new()).await
+    }).await;
+    assert!(res.is_err_and(|e| e.is_panic()));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Panicked));
+    assert_eq!(manager.query_progress(&task_id), None);
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+}
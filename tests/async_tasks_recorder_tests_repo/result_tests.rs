@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_result_launch_check(task_num: usize) {
+    let manager = AsyncTasksResultRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for i in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        let task = async move {
+            let latency = fastrand::u64(5..30);
+            tokio::time::sleep(tokio::time::Duration::from_millis(latency)).await;
+            if i % 2 == 0 {
+                Ok(i)
+            } else {
+                Err(format!("error {}", i))
+            }
+        };
+
+        join_set.spawn(async move {
+            // launch
+            assert_eq!(manager.query_task_result(&task_id).await, (TaskState::NotFound, None),
+                       "Initial state should be NotFound without result {}", task_id);
+            let res = manager.launch(task_id.clone(), task).await;
+            assert!(res.is_ok(),
+                    "Launch should success {}", task_id);
+
+            loop {
+                match manager.query_task_result_cloned(&task_id).await {
+                    (TaskState::Success, res) => {
+                        assert_eq!(res, Some(Ok(i)), "Unexpected result {}", task_id);
+                        break;
+                    }
+                    (TaskState::Failed, res) => {
+                        assert_eq!(res, Some(Err(format!("error {}", i))), "Unexpected result {}", task_id);
+                        break;
+                    }
+                    (TaskState::Working, res) => {
+                        assert_eq!(res, None, "Working task shouldn't have result {}", task_id);
+                        tokio::time::sleep(tokio::time::Duration::from_micros(50)).await;
+                    }
+                    (state, _) => {
+                        panic!("Unexpected task state {}: {:?}", task_id, state);
+                    }
+                }
+            }
+
+            // relaunch the failed task
+            if i % 2 != 0 {
+                let res = manager.launch_block(task_id.clone(), async move { Ok(i) }).await;
+                assert_eq!(res.ok().flatten().as_deref(), Some(&Ok(i)), "Relaunch should success {}", task_id);
+            }
+            assert_eq!(manager.query_task_result_cloned(&task_id).await, (TaskState::Success, Some(Ok(i))));
+
+            // revoke
+            let res = manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await;
+            assert!(res.is_ok());
+            assert_eq!(manager.query_task_result(&task_id).await, (TaskState::NotFound, None),
+                       "Revoked task shouldn't have result {}", task_id);
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+}
+
+pub async fn test_get_or_launch(task_num: usize, caller_num: usize) {
+    let manager = AsyncTasksResultRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for i in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+
+        join_set.spawn(async move {
+            // only one of the concurrent callers executes the task
+            let executed = Arc::new(AtomicUsize::new(0));
+            let mut callers = tokio::task::JoinSet::new();
+            for _ in 0..caller_num {
+                let manager = manager.clone();
+                let task_id = task_id.clone();
+                let executed = executed.clone();
+                callers.spawn(async move {
+                    let task = async move {
+                        executed.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(fastrand::u64(5..30))).await;
+                        Ok::<usize, String>(i)
+                    };
+                    manager.get_or_launch(task_id, task).await
+                        .unwrap_or_else(|(refusal, _)| panic!("Should not be refused: {:?}", refusal))
+                });
+            }
+            let mut outputs = Vec::new();
+            while let Some(res) = callers.join_next().await {
+                outputs.push(res.unwrap().expect("Should have output"));
+            }
+            assert_eq!(executed.load(Ordering::SeqCst), 1, "Task should be executed once {}", task_id);
+            assert!(outputs.iter().all(|output| Arc::ptr_eq(output, &outputs[0])),
+                    "Output should be shared {}", task_id);
+            assert_eq!(*outputs[0], Ok(i));
+
+            // `Success` returns immediately without executing
+            let res = manager.get_or_launch(task_id.clone(), async { panic!("Should not be executed") }).await;
+            assert!(res.is_ok_and(|output| Arc::ptr_eq(&output.unwrap(), &outputs[0])));
+
+            // `Revoking` is refused
+            let manager_cloned = manager.clone();
+            let task_id_cloned = task_id.clone();
+            let revoke = tokio::spawn(async move {
+                manager_cloned.revoke_task_block(&task_id_cloned, async {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok::<(), ()>(())
+                }).await.is_ok()
+            });
+            manager.get_recorder_ref().wait_for_state(&task_id, &[TaskState::Revoking]).await;
+            let res = manager.get_or_launch(task_id.clone(), async move { Ok(i) }).await;
+            assert_eq!(res.err().and_then(|(refusal, _)| refusal.state().cloned()), Some(TaskState::Revoking));
+            assert!(revoke.await.unwrap());
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+}
+
+pub async fn test_result_expiry(task_num: usize) {
+    let recorder = AsyncTasksRecorder::new()
+        .with_success_ttl(Duration::from_millis(20))
+        .with_failed_ttl(Duration::from_millis(20));
+    let manager = AsyncTasksResultRecorder::<String, usize, usize>::new_with_recorder(recorder);
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut task_ids = Vec::new();
+    for i in 0..task_num {
+        let task_id = task_id_generator();
+        let task = async move { if i % 2 == 0 { Ok(i) } else { Err(i) } };
+        assert!(manager.launch_block(task_id.clone(), task).await.is_ok());
+        task_ids.push(task_id);
+    }
+    assert_eq!(manager.get_results_ref().len(), task_num);
+
+    // removed by sweeping
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    assert_eq!(manager.get_recorder_ref().sweep_expired().await.len(), task_num);
+    assert_eq!(manager.get_results_ref().len(), 0, "Outputs of the expired tasks should be removed");
+
+    // removed by querying and relaunching
+    assert!(manager.launch_block(task_ids[0].clone(), async { Ok(1) }).await.is_ok());
+    assert!(manager.launch_block(task_ids[1].clone(), async { Ok(2) }).await.is_ok());
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    assert_eq!(manager.query_task_result(&task_ids[0]).await, (TaskState::NotFound, None));
+    let res = manager.launch(task_ids[1].clone(), async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        Ok(3)
+    }).await;
+    assert!(res.is_ok());
+    assert_eq!(manager.get_results_ref().len(), 0);
+}
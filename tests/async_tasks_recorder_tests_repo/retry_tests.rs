@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+/// Return a factory of tasks which fail before the `succeed_at`th attempt.
+fn get_flaky_task_factory(succeed_at: usize) -> (Arc<AtomicUsize>, impl FnMut() -> std::future::Ready<Result<usize, String>>) {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let counter_cloned = counter.clone();
+    let factory = move || {
+        let attempt = counter_cloned.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt >= succeed_at {
+            std::future::ready(Ok(attempt))
+        } else {
+            std::future::ready(Err(format!("attempt {}", attempt)))
+        }
+    };
+    (counter, factory)
+}
+
+pub async fn test_retry_launch() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // succeed after retries
+    let task_id = task_id_generator();
+    let (counter, factory) = get_flaky_task_factory(3);
+    let policy = RetryPolicy::new(5)
+        .with_backoff(Backoff::Fixed(Duration::from_millis(20)));
+    assert!(manager.launch_with_retry(task_id.clone(), factory, policy, LaunchOptions::new()).await.is_ok());
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Working,
+               "Should keep Working between attempts {}", task_id);
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+    assert_eq!(manager.query_task_attempts(&task_id).await, Some(3));
+    assert_eq!(counter.load(Ordering::SeqCst), 3);
+
+    // fail after the policy is exhausted
+    let task_id = task_id_generator();
+    let (counter, factory) = get_flaky_task_factory(usize::MAX);
+    let res = manager.launch_block_with_retry(task_id.clone(), factory, RetryPolicy::new(4), LaunchOptions::new()).await;
+    assert_eq!(res.ok(), Some(Some(Err("attempt 4".to_string()))));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Error));
+    assert_eq!(manager.query_task_attempts(&task_id).await, Some(4));
+    assert_eq!(counter.load(Ordering::SeqCst), 4);
+
+    // not retry on certain errors
+    let (counter, factory) = get_flaky_task_factory(usize::MAX);
+    let policy = RetryPolicy::new(4)
+        .with_retry_if(|err: &String| err != "attempt 2");
+    let res = manager.launch_block_with_retry(task_id.clone(), factory, policy, LaunchOptions::new()).await;
+    assert_eq!(res.ok(), Some(Some(Err("attempt 2".to_string()))));
+    assert_eq!(manager.query_task_attempts(&task_id).await, Some(2));
+    assert_eq!(counter.load(Ordering::SeqCst), 2);
+
+    // a delay too large to represent
+    let task_id = task_id_generator();
+    let (_, factory) = get_flaky_task_factory(usize::MAX);
+    let policy = RetryPolicy::new(2)
+        .with_backoff(Backoff::Fixed(Duration::MAX));
+    assert!(manager.launch_with_retry(task_id.clone(), factory, policy, LaunchOptions::new()).await.is_ok());
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Working);
+    assert_eq!(manager.abort_task(&task_id).await, Ok(()));
+
+    // timed out as a whole, including the delays between attempts
+    let task_id = task_id_generator();
+    let (counter, factory) = get_flaky_task_factory(usize::MAX);
+    let policy = RetryPolicy::new(5)
+        .with_backoff(Backoff::Fixed(Duration::from_millis(20)));
+    let options = LaunchOptions::new().with_timeout(Duration::from_millis(30));
+    let res = manager.launch_block_with_retry(task_id.clone(), factory, policy, options).await;
+    assert_eq!(res.ok(), Some(None));
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::TimedOut));
+    assert_eq!(counter.load(Ordering::SeqCst), 2);
+
+    // normal launch has no attempts
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_attempts(&task_id).await, None);
+}
+
+pub fn test_retry_policy_delay() {
+    let policy = RetryPolicy::<()>::new(10)
+        .with_backoff(Backoff::Exponential {
+            initial: Duration::from_millis(10),
+            factor: 2,
+            max: Duration::from_millis(100),
+        });
+    let delays: Vec<_> = (1..=6).map(|retry| policy.delay(retry).as_millis()).collect();
+    assert_eq!(delays, vec![10, 20, 40, 80, 100, 100]);
+    assert_eq!(policy.delay(1000), Duration::from_millis(100));
+    assert!(policy.should_retry(9, &()));
+    assert!(!policy.should_retry(10, &()));
+
+    let policy = policy.with_jitter(true);
+    for retry in 1..=6 {
+        let delay = policy.delay(retry).as_millis();
+        let max = [10, 20, 40, 80, 100, 100][retry - 1];
+        assert!(max / 2 <= delay && delay <= max, "Delay with jitter out of range: {}", delay);
+    }
+
+    assert_eq!(RetryPolicy::<()>::new(0).max_attempts(), 1);
+}
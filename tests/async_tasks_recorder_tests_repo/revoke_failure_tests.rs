@@ -0,0 +1,91 @@
+use std::cell::Cell;
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_revoke_failed_policy() {
+    let manager = AsyncTasksRecorder::new()
+        .with_revoke_failure_policy(RevokeFailurePolicy::RevokeFailed)
+        .with_panic_hook(|_, _| {});
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // the error is kept
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    let res = manager.revoke_task_block(&task_id, async { Err::<(), &str>("half deleted") }).await;
+    assert_eq!(res.ok(), Some(Err("half deleted")));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::RevokeFailed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Error));
+    let res = manager.launch(task_id.clone(), async { Ok::<(), ()>(()) }).await;
+    assert_eq!(res.err().and_then(|(refusal, _)| refusal.state().cloned()), Some(TaskState::RevokeFailed));
+
+    // retry the revoking
+    let res = manager.revoke_task_block_with_timeout(&task_id, async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }, Duration::from_millis(20)).await;
+    assert_eq!(res.ok(), Some(None));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::RevokeFailed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::TimedOut));
+    let revoke_task = async {
+        if true {
+            panic!("revoke panic");
+        }
+        Ok::<(), ()>(())
+    };
+    assert!(manager.revoke_task(&task_id, revoke_task).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::RevokeFailed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Panicked));
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound);
+
+    // back to `Success` forcefully
+    let task_id = task_id_generator();
+    assert_eq!(manager.modify_to_success_after_revoke_failed(task_id.clone()).await, Err(TaskState::NotFound));
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.modify_to_success_after_revoke_failed(task_id.clone()).await, Err(TaskState::Success));
+    assert!(manager.revoke_task_block(&task_id, async { Err::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.modify_to_success_after_revoke_failed(task_id.clone()).await, Ok(()));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+    assert_eq!(manager.query_failure_reason(&task_id).await, None);
+}
+
+pub async fn test_revoke_failure_policies() {
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // `Failed` can be launched again
+    let manager = AsyncTasksRecorder::new()
+        .with_revoke_failure_policy(RevokeFailurePolicy::Failed);
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&task_id, async { Err::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Error));
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+
+    // restored by default
+    let manager = AsyncTasksRecorder::new();
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task_block(&task_id, async { Err::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+    assert_eq!(manager.query_failure_reason(&task_id).await, None);
+
+    // the error of a revoking executed asynchronously is recorded
+    assert!(manager.revoke_task(&task_id, async { Err::<(), &str>("disk busy") }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+    let error = manager.query_revoke_error(&task_id).await.unwrap();
+    assert_eq!(error.with_downcast(|e: &&str| *e), Some("disk busy"));
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.query_revoke_error(&task_id).await.is_none());
+
+    // the error is not required to be `Sync`
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task(&task_id, async { Err::<(), _>(Cell::new(7)) }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+    let error = manager.query_revoke_error(&task_id).await.unwrap();
+    assert!(error.is::<Cell<i32>>());
+    assert_eq!(error.with_downcast(|e: &Cell<i32>| e.get()), Some(7));
+}
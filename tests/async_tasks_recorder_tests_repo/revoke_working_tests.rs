@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_revoke_working_abort() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // the working task is aborted before revoking
+    let task_id = task_id_generator();
+    let finished = Arc::new(AtomicBool::new(false));
+    let finished_cloned = finished.clone();
+    assert!(manager.launch(task_id.clone(), async move {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        finished_cloned.store(true, Ordering::SeqCst);
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    let res = manager.revoke_task_even_working_block(&task_id, async { Ok::<(), ()>(()) }, WhenWorking::Abort).await;
+    assert_eq!(res.ok(), Some(Some(Ok(()))));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound);
+    assert!(!finished.load(Ordering::SeqCst), "Task should be aborted");
+
+    // back to `Failed` if the revoking fails
+    let task_id = task_id_generator();
+    assert!(manager.launch(task_id.clone(), async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    let res = manager.revoke_task_even_working_block(&task_id, async { Err::<(), ()>(()) }, WhenWorking::Abort).await;
+    assert_eq!(res.ok(), Some(Some(Err(()))));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Aborted));
+
+    // same as `revoke_task` if the task is not working
+    let res = manager.revoke_task_even_working(&task_id, async { Ok::<(), ()>(()) }, WhenWorking::Abort).await;
+    assert_eq!(res.err().and_then(|(refusal, _)| refusal.state().cloned()), Some(TaskState::Failed));
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    let res = manager.revoke_task_even_working_block(&task_id, async { Ok::<(), ()>(()) }, WhenWorking::Abort).await;
+    assert_eq!(res.ok(), Some(Some(Ok(()))));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound);
+}
+
+pub async fn test_revoke_working_after_success() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // revoked after the task succeeds
+    let task_id = task_id_generator();
+    let finished = Arc::new(AtomicBool::new(false));
+    let finished_cloned = finished.clone();
+    assert!(manager.launch(task_id.clone(), async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        finished_cloned.store(true, Ordering::SeqCst);
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    let finished_cloned = finished.clone();
+    let res = manager.revoke_task_even_working(&task_id, async move {
+        assert!(finished_cloned.load(Ordering::SeqCst), "Revoking should start after the task succeeds");
+        Ok::<(), ()>(())
+    }, WhenWorking::AfterSuccess).await;
+    assert!(res.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Revoking);
+    let res = manager.launch(task_id.clone(), async { Ok::<(), ()>(()) }).await;
+    assert_eq!(res.err().and_then(|(refusal, _)| refusal.state().cloned()), Some(TaskState::Revoking),
+               "Should not launch before revoked");
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::NotFound);
+    assert!(finished.load(Ordering::SeqCst));
+
+    // removed without revoking if the task fails
+    let task_id = task_id_generator();
+    assert!(manager.launch(task_id.clone(), async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Err::<(), ()>(())
+    }).await.is_ok());
+    let revoked = Arc::new(AtomicBool::new(false));
+    let revoked_cloned = revoked.clone();
+    let res = manager.revoke_task_even_working_block(&task_id, async move {
+        revoked_cloned.store(true, Ordering::SeqCst);
+        Ok::<(), ()>(())
+    }, WhenWorking::AfterSuccess).await;
+    assert_eq!(res.ok(), Some(None));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound);
+    assert!(!revoked.load(Ordering::SeqCst), "Should not revoke a failed task");
+}
+
+pub async fn test_revoke_working_race(task_num: usize) {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for i in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+
+        join_set.spawn(async move {
+            let res = manager.launch(task_id.clone(), async {
+                tokio::time::sleep(Duration::from_millis(fastrand::u64(1..20))).await;
+                Ok::<(), ()>(())
+            }).await;
+            assert!(res.is_ok(), "Launch should success {}", task_id);
+            tokio::time::sleep(Duration::from_millis(fastrand::u64(0..20))).await;
+
+            let when_working = match i % 2 {
+                0 => WhenWorking::Abort,
+                _ => WhenWorking::AfterSuccess,
+            };
+            let res = manager.revoke_task_even_working_block(&task_id, async { Ok::<(), ()>(()) }, when_working).await;
+            assert!(matches!(res, Ok(Some(Ok(())))), "Revoking should success {}", task_id);
+            assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound);
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+}
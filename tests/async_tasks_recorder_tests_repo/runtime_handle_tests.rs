@@ -0,0 +1,76 @@
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+const IO_THREAD_NAME: &str = "io-runtime-worker";
+
+fn assert_on_io_runtime() {
+    assert_eq!(std::thread::current().name(), Some(IO_THREAD_NAME),
+               "Task should be executed on the runtime of the handle");
+}
+
+pub async fn test_runtime_handle(task_num: usize) {
+    let io_runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name(IO_THREAD_NAME)
+        .enable_all()
+        .build().unwrap();
+    let manager = AsyncTasksRecorder::new()
+        .with_runtime_handle(io_runtime.handle().clone());
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for _ in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        join_set.spawn(async move {
+            let res = manager.launch(task_id.clone(), async {
+                assert_on_io_runtime();
+                tokio::time::sleep(Duration::from_millis(fastrand::u64(1..20))).await;
+                Ok::<(), ()>(())
+            }).await;
+            assert!(res.is_ok(), "Launch should success {}", task_id);
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success,
+                       "Task should not panic {}", task_id);
+
+            let res = manager.revoke_task(&task_id, async {
+                assert_on_io_runtime();
+                Ok::<(), ()>(())
+            }).await;
+            assert!(res.is_ok());
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::NotFound,
+                       "Revoking should not panic {}", task_id);
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+
+    // called from a runtime without time driver
+    let task_id = task_id_generator();
+    let manager_cloned = manager.clone();
+    std::thread::spawn(move || {
+        let bare_runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        bare_runtime.block_on(async move {
+            let res = manager_cloned.launch_block_with_options(task_id.clone(), async {
+                std::future::pending::<()>().await;
+                Ok::<(), ()>(())
+            }, LaunchOptions::new().with_timeout(Duration::from_millis(10))).await;
+            assert!(matches!(res, Ok(None)), "Task should be timed out {}", task_id);
+
+            assert!(manager_cloned.launch(task_id.clone(), async {
+                assert_on_io_runtime();
+                Ok::<(), ()>(())
+            }).await.is_ok());
+            assert_eq!(manager_cloned.wait_for_finish(&task_id).await, TaskState::Success);
+        });
+    }).join().unwrap();
+
+    io_runtime.shutdown_background();
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_snapshot_and_restore() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let working_id = task_id_generator();
+    assert!(manager.launch(working_id.clone(), async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    let revoking_id = task_id_generator();
+    assert!(manager.launch_block(revoking_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert!(manager.revoke_task(&revoking_id, async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    let success_id = task_id_generator();
+    assert!(manager.launch_block(success_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    let failed_id = task_id_generator();
+    assert!(manager.launch_block(failed_id.clone(), async { Err::<(), ()>(()) }).await.is_ok());
+
+    let mut snapshot = manager.snapshot().await;
+    snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut expected = vec![
+        (working_id.clone(), TaskState::Working),
+        (revoking_id.clone(), TaskState::Revoking),
+        (success_id.clone(), TaskState::Success),
+        (failed_id.clone(), TaskState::Failed),
+    ];
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(snapshot, expected);
+
+    // restore with default options
+    let restored = AsyncTasksRecorder::restore(snapshot.clone(), &RestoreOptions::default());
+    assert_eq!(restored.query_task_state(&working_id).await, TaskState::Failed);
+    assert_eq!(restored.query_task_state(&revoking_id).await, TaskState::Success);
+    assert_eq!(restored.query_task_state(&success_id).await, TaskState::Success);
+    assert_eq!(restored.query_task_state(&failed_id).await, TaskState::Failed);
+    assert!(restored.launch_block(working_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok(),
+            "Restored Failed task should be able to launch again {}", working_id);
+
+    // drop the unfinished tasks
+    let options = RestoreOptions {
+        working_as: TaskState::NotFound,
+        revoking_as: TaskState::NotFound,
+    };
+    let restored = AsyncTasksRecorder::restore(snapshot, &options);
+    assert_eq!(restored.get_recorder_ref().len(), 2);
+    assert_eq!(restored.query_task_state(&working_id).await, TaskState::NotFound);
+    assert_eq!(restored.query_task_state(&revoking_id).await, TaskState::NotFound);
+}
+
+#[cfg(feature = "serde")]
+pub async fn test_snapshot_to_and_restore_from() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let working_id = task_id_generator();
+    assert!(manager.launch(working_id.clone(), async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }).await.is_ok());
+    let success_id = task_id_generator();
+    assert!(manager.launch_block(success_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+
+    let mut buf = Vec::new();
+    manager.snapshot_to(&mut buf).await.unwrap();
+
+    let restored = AsyncTasksRecorder::<String>::restore_from(buf.as_slice(), &RestoreOptions::default()).unwrap();
+    assert_eq!(restored.get_recorder_ref().len(), 2);
+    assert_eq!(restored.query_task_state(&working_id).await, TaskState::Failed);
+    assert_eq!(restored.query_task_state(&success_id).await, TaskState::Success);
+
+    assert!(AsyncTasksRecorder::<String>::restore_from(&buf[..buf.len() - 1], &RestoreOptions::default()).is_err(),
+            "Broken snapshot should not be restored");
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_executor::Executor;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_smol_spawner(executor: Arc<Executor<'static>>, task_num: usize) {
+    let manager = AsyncTasksRecorder::new()
+        .with_spawner(SmolSpawner::new(executor.clone()));
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut handles = Vec::new();
+    for _ in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        handles.push(executor.spawn(async move {
+            // launch asynchronously
+            let res = manager.launch(task_id.clone(), async {
+                async_io::Timer::after(Duration::from_millis(fastrand::u64(1..20))).await;
+                Ok::<(), ()>(())
+            }).await;
+            assert!(res.is_ok(), "Launch should success {}", task_id);
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+
+            // revoke asynchronously
+            assert!(manager.revoke_task(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::NotFound);
+
+            // timer
+            let res = manager.launch_block_with_options(task_id.clone(), async {
+                async_io::Timer::after(Duration::from_secs(10)).await;
+                Ok::<(), ()>(())
+            }, LaunchOptions::new().with_timeout(Duration::from_millis(10))).await;
+            assert!(matches!(res, Ok(None)), "Task should be timed out {}", task_id);
+            assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::TimedOut));
+        }));
+    }
+
+    for handle in handles {
+        handle.await;
+    }
+}
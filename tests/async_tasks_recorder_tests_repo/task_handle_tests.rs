@@ -0,0 +1,103 @@
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_task_handle(task_num: usize) {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for i in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+
+        join_set.spawn(async move {
+            let task = async move {
+                tokio::time::sleep(Duration::from_millis(fastrand::u64(5..30))).await;
+                if i % 2 == 0 {
+                    Ok(i)
+                } else {
+                    Err(format!("error {}", i))
+                }
+            };
+            let handle = manager.launch_with_handle(task_id.clone(), task, LaunchOptions::new()).await
+                .unwrap_or_else(|(refusal, _)| panic!("Launch should success {}: {:?}", task_id, refusal));
+            assert_eq!(handle.task_id(), &task_id);
+            assert_eq!(handle.state().await, TaskState::Working);
+            let res = manager.launch_with_handle(task_id.clone(), async move { Ok::<usize, String>(i) }, LaunchOptions::new()).await;
+            assert_eq!(res.err().and_then(|(refusal, _)| refusal.state().cloned()), Some(TaskState::Working));
+
+            match i % 2 {
+                0 => {
+                    assert_eq!(handle.await.ok(), Some(i), "Unexpected output {}", task_id);
+                    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+                }
+                _ => {
+                    let error = handle.await.err().and_then(TaskError::into_error);
+                    assert_eq!(error, Some(format!("error {}", i)), "Unexpected output {}", task_id);
+                    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+                }
+            }
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+}
+
+pub async fn test_task_handle_abort_and_detach() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // abort
+    let task_id = task_id_generator();
+    let handle = manager.launch_with_handle(task_id.clone(), async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }, LaunchOptions::new()).await.ok().unwrap();
+    assert_eq!(handle.abort().await, Ok(()));
+    assert_eq!(handle.state().await, TaskState::Failed);
+    assert_eq!(handle.abort().await, Err(TaskState::Failed));
+    assert!(matches!(handle.await, Err(TaskError::Aborted)));
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Aborted));
+
+    // a finished handle does not abort the next run
+    let handle = manager.launch_with_handle(task_id.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new()).await.ok().unwrap();
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+    manager.modify_state_force(task_id.clone(), TaskState::Failed).await;
+    let next_handle = manager.launch_with_handle(task_id.clone(), async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok::<(), ()>(())
+    }, LaunchOptions::new()).await.ok().unwrap();
+    assert_eq!(handle.abort().await, Err(TaskState::Working));
+    assert!(next_handle.await.is_ok());
+    assert!(handle.await.is_ok());
+
+    // panic
+    let task_id = task_id_generator();
+    let handle = manager.launch_with_handle(task_id.clone(), async {
+        if true {
+            panic!("handle panic");
+        }
+        Ok::<(), ()>(())
+    }, LaunchOptions::new()).await.ok().unwrap();
+    let error = handle.await.unwrap_err();
+    assert_eq!(error.reason(), FailureReason::Panicked);
+    assert!(matches!(error, TaskError::Panicked(payload) if payload.downcast_ref::<&str>() == Some(&"handle panic")));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Failed);
+
+    // detach
+    let task_id = task_id_generator();
+    let handle = manager.launch_with_handle(task_id.clone(), async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok::<(), ()>(())
+    }, LaunchOptions::new()).await.ok().unwrap();
+    handle.detach();
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+}
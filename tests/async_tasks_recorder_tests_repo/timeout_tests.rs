@@ -0,0 +1,76 @@
+use std::time::Duration;
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_timeout_launch_revoke() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // timed out by `launch_with_options`
+    let task_id = task_id_generator();
+    let task = async {
+        tokio::time::sleep(Duration::from_secs(100)).await;
+        Ok::<(), ()>(())
+    };
+    assert!(manager.launch_with_options(task_id.clone(), task, LaunchOptions::new().with_timeout(Duration::from_millis(10))).await.is_ok());
+    assert_eq!(manager.query_failure_reason(&task_id).await, None);
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Failed,
+               "Timed out task should be Failed {}", task_id);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::TimedOut));
+
+    // finished within the timeout
+    let res = manager.launch_block_with_options(task_id.clone(), async { Err::<(), ()>(()) }, LaunchOptions::new().with_timeout(Duration::from_secs(100))).await;
+    assert!(matches!(res, Ok(Some(Err(())))));
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Error));
+    let res = manager.launch_block_with_options(task_id.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_timeout(Duration::from_secs(100))).await;
+    assert!(matches!(res, Ok(Some(Ok(())))));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+    assert_eq!(manager.query_failure_reason(&task_id).await, None);
+
+    // revoking is timed out
+    let revoke_task = async {
+        tokio::time::sleep(Duration::from_secs(100)).await;
+        Ok::<(), ()>(())
+    };
+    let res = manager.revoke_task_block_with_timeout(&task_id, revoke_task, Duration::from_millis(10)).await;
+    assert!(matches!(res, Ok(None)));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success,
+               "Timed out revoking should make task Success {}", task_id);
+    let revoke_task = async {
+        tokio::time::sleep(Duration::from_secs(100)).await;
+        Ok::<(), ()>(())
+    };
+    assert!(manager.revoke_task_with_timeout(&task_id, revoke_task, Duration::from_millis(10)).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Revoking);
+    assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::Success);
+
+    // revoking finishes within the timeout
+    let res = manager.revoke_task_block_with_timeout(&task_id, async { Ok::<(), ()>(()) }, Duration::from_secs(100)).await;
+    assert!(matches!(res, Ok(Some(Ok(())))));
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::NotFound);
+
+    // timed out by `launch_block_with_options`
+    let task = async {
+        tokio::time::sleep(Duration::from_secs(100)).await;
+        Ok::<(), ()>(())
+    };
+    let res = manager.launch_block_with_options(task_id.clone(), task, LaunchOptions::new().with_timeout(Duration::from_millis(10))).await;
+    assert!(matches!(res, Ok(None)));
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::TimedOut));
+
+    // aborted before timeout
+    let task = async {
+        tokio::time::sleep(Duration::from_secs(100)).await;
+        Ok::<(), ()>(())
+    };
+    assert!(manager.launch_with_options(task_id.clone(), task, LaunchOptions::new().with_timeout(Duration::from_secs(100))).await.is_ok());
+    assert_eq!(manager.abort_task(&task_id).await, Ok(()));
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Aborted));
+
+    // too large to be a deadline
+    let res = manager.launch_block_with_options(task_id.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_timeout(Duration::MAX)).await;
+    assert!(matches!(res, Ok(Some(Ok(())))));
+    let res = manager.revoke_task_block_with_timeout(&task_id, async { Ok::<(), ()>(()) }, Duration::MAX).await;
+    assert!(matches!(res, Ok(Some(Ok(())))));
+}
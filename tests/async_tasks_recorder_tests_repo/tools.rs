@@ -1,6 +1,6 @@
 use std::future::Future;
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::enum_variant_names)]
 pub enum RuntimeType {
     CurrentThread,
     CustomThread(usize),
@@ -9,25 +9,24 @@ pub enum RuntimeType {
 
 pub fn do_async_test<Fut>(runtime_type: RuntimeType, test_future: Fut)
     where Fut: Future {
-    let runtime;
-    match runtime_type {
+    let runtime = match runtime_type {
         RuntimeType::CurrentThread => {
-            runtime = tokio::runtime::Builder::new_current_thread()
+            tokio::runtime::Builder::new_current_thread()
                 .enable_all()
-                .build().unwrap();
+                .build().unwrap()
         }
         RuntimeType::CustomThread(thread_num) => {
-            runtime = tokio::runtime::Builder::new_multi_thread()
+            tokio::runtime::Builder::new_multi_thread()
                 .worker_threads(thread_num)
                 .enable_all()
-                .build().unwrap();
+                .build().unwrap()
         }
         RuntimeType::MultiThread => {
-            runtime = tokio::runtime::Builder::new_multi_thread()
+            tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
-                .build().unwrap();
+                .build().unwrap()
         }
-    }
+    };
 
     runtime.block_on(test_future);
 }
@@ -43,3 +42,22 @@ pub fn get_task_id_generator() -> impl FnMut() -> String {
     }
 }
 
+
+/// Run the future returned by `test` on a `smol` executor with 4 threads.
+#[cfg(feature = "runtime-smol")]
+pub fn do_smol_test<F, Fut>(test: F)
+    where F: FnOnce(std::sync::Arc<async_executor::Executor<'static>>) -> Fut,
+          Fut: Future {
+    let executor = std::sync::Arc::new(async_executor::Executor::new());
+    // the worker threads stop when the sender is dropped
+    let (stop_tx, stop_rx) = tokio::sync::watch::channel(());
+    std::thread::scope(|s| {
+        for _ in 0..3 {
+            let executor = executor.clone();
+            let mut stop_rx = stop_rx.clone();
+            s.spawn(move || async_io::block_on(executor.run(stop_rx.changed())));
+        }
+        async_io::block_on(executor.run(test(executor.clone())));
+        drop(stop_tx);
+    });
+}
@@ -0,0 +1,166 @@
+use async_tasks_state_map::*;
+
+use super::tools;
+
+/// The states entered by the recorder itself, and the ones of the workflows below.
+#[derive(Eq, PartialEq, Debug, Clone)]
+enum WorkflowState {
+    Working,
+    Queued,
+    Success,
+    Failed,
+    NotFound,
+    Revoking,
+    RevokeFailed,
+    AwaitingApproval,
+    Rejected,
+    NeedsReview,
+}
+
+impl RecordedState for WorkflowState {
+    fn working() -> Self {
+        WorkflowState::Working
+    }
+
+    fn queued() -> Self {
+        WorkflowState::Queued
+    }
+
+    fn success() -> Self {
+        WorkflowState::Success
+    }
+
+    fn failed() -> Self {
+        WorkflowState::Failed
+    }
+
+    fn not_found() -> Self {
+        WorkflowState::NotFound
+    }
+
+    fn revoking() -> Self {
+        WorkflowState::Revoking
+    }
+
+    fn revoke_failed() -> Self {
+        WorkflowState::RevokeFailed
+    }
+}
+
+/// An uploaded file waits for approval before it is `Success`,
+/// and a rejected one can be uploaded again or deleted.
+struct ApprovalWorkflow;
+
+impl TransitionTable<String, WorkflowState> for ApprovalWorkflow {
+    fn can_launch(&self, _task_id: &String, state: &WorkflowState) -> bool {
+        matches!(state, WorkflowState::NotFound | WorkflowState::Failed | WorkflowState::Rejected)
+    }
+
+    fn succeeded_state(&self, _task_id: &String) -> WorkflowState {
+        WorkflowState::AwaitingApproval
+    }
+
+    fn can_revoke(&self, _task_id: &String, state: &WorkflowState) -> bool {
+        *state == WorkflowState::Success || *state == WorkflowState::Rejected
+    }
+
+    fn can_transit(&self, _task_id: &String, from: &WorkflowState, to: &WorkflowState) -> bool {
+        *from == WorkflowState::AwaitingApproval && (*to == WorkflowState::Success || *to == WorkflowState::Rejected)
+    }
+}
+
+/// A task returning `Err` needs review before launched again, while the other failures can be retried at once.
+struct ReviewWorkflow;
+
+impl TransitionTable<String, WorkflowState> for ReviewWorkflow {
+    fn failed_state(&self, _task_id: &String, reason: &FailureReason) -> WorkflowState {
+        match reason {
+            FailureReason::Error => WorkflowState::NeedsReview,
+            _ => WorkflowState::Failed,
+        }
+    }
+
+    fn can_transit(&self, _task_id: &String, from: &WorkflowState, to: &WorkflowState) -> bool {
+        *from == WorkflowState::NeedsReview && *to == WorkflowState::Failed
+    }
+}
+
+pub async fn test_transition_table() {
+    let manager = AsyncTasksRecorder::default().with_transition_table(ApprovalWorkflow);
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // succeed to the custom state
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::AwaitingApproval);
+    let res = manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await;
+    assert_eq!(res.err().and_then(|(refusal, _)| refusal.state().cloned()), Some(WorkflowState::AwaitingApproval),
+               "Should not revoke a task awaiting approval");
+    assert_eq!(manager.transit(task_id.clone(), WorkflowState::Failed).await, Err(WorkflowState::AwaitingApproval));
+
+    // approve
+    assert_eq!(manager.transit(task_id.clone(), WorkflowState::Success).await, Ok(WorkflowState::AwaitingApproval));
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::Success);
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::NotFound);
+
+    // reject, then launch again
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.wait_for_finish(&task_id).await, WorkflowState::AwaitingApproval);
+    assert_eq!(manager.transit(task_id.clone(), WorkflowState::Rejected).await, Ok(WorkflowState::AwaitingApproval));
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::AwaitingApproval);
+
+    // a failed revoking goes back to the state before revoking
+    assert_eq!(manager.transit(task_id.clone(), WorkflowState::Rejected).await, Ok(WorkflowState::AwaitingApproval));
+    assert!(manager.revoke_task_block(&task_id, async { Err::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::Rejected);
+    assert!(manager.revoke_task_block(&task_id, async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::NotFound);
+
+    // the in-progress states are never transited
+    let task_id = task_id_generator();
+    assert_eq!(manager.transit(task_id.clone(), WorkflowState::Working).await, Err(WorkflowState::NotFound));
+}
+
+pub async fn test_default_transitions_refuse_transit() {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Ok::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, TaskState::Success);
+    assert_eq!(manager.transit(task_id.clone(), TaskState::Failed).await, Err(TaskState::Success));
+    assert_eq!(manager.transit(task_id.clone(), TaskState::NotFound).await, Err(TaskState::Success));
+}
+
+pub async fn test_transition_table_failed_state() {
+    let manager = AsyncTasksRecorder::default().with_transition_table(ReviewWorkflow);
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    // fail to the custom state
+    let task_id = task_id_generator();
+    assert!(manager.launch_block(task_id.clone(), async { Err::<(), ()>(()) }).await.is_ok());
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::NeedsReview);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::Error));
+    let res = manager.launch(task_id.clone(), async { Ok::<(), ()>(()) }).await;
+    assert_eq!(res.err().and_then(|(refusal, _)| refusal.state().cloned()), Some(WorkflowState::NeedsReview));
+
+    // the dependents regard it as failed
+    let dependent = task_id_generator();
+    assert_eq!(manager.launch_block_with_options(dependent.clone(), async { Ok::<(), ()>(()) }, LaunchOptions::new().with_prerequisites([task_id.clone()])).await.ok(), Some(None));
+    assert_eq!(manager.query_failure_reason(&dependent).await, Some(FailureReason::DependencyFailed));
+    assert_eq!(manager.query_task_state(&dependent).await, WorkflowState::Failed);
+
+    // reviewed, then launch again
+    assert_eq!(manager.transit(task_id.clone(), WorkflowState::Failed).await, Ok(WorkflowState::NeedsReview));
+    assert_eq!(manager.query_failure_reason(&task_id).await, None);
+    let res = manager.launch_block_with_options(task_id.clone(), async {
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+        Ok::<(), ()>(())
+    }, LaunchOptions::new().with_timeout(std::time::Duration::from_millis(20))).await;
+    assert_eq!(res.ok(), Some(None));
+    assert_eq!(manager.query_task_state(&task_id).await, WorkflowState::Failed);
+    assert_eq!(manager.query_failure_reason(&task_id).await, Some(FailureReason::TimedOut));
+}
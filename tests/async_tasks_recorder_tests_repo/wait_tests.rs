@@ -0,0 +1,68 @@
+use async_tasks_state_map::*;
+
+use super::tools;
+
+pub async fn test_wait_launch_revoke(task_num: usize) {
+    let manager = AsyncTasksRecorder::new();
+    let mut task_id_generator = tools::get_task_id_generator();
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for i in 0..task_num {
+        let manager = manager.clone();
+        let task_id = task_id_generator();
+        let task = async move {
+            let latency = fastrand::u64(5..30);
+            tokio::time::sleep(tokio::time::Duration::from_millis(latency)).await;
+            if i % 3 == 0 {
+                Err(())
+            } else {
+                Ok(())
+            }
+        };
+
+        join_set.spawn(async move {
+            // wait before launch
+            let waiter = {
+                let manager = manager.clone();
+                let task_id = task_id.clone();
+                tokio::spawn(async move {
+                    manager.wait_for_state(&task_id, &[TaskState::Success, TaskState::Failed]).await
+                })
+            };
+            assert_eq!(manager.wait_for_finish(&task_id).await, TaskState::NotFound,
+                       "Should return immediately before launch {}", task_id);
+
+            // launch
+            let res = manager.launch(task_id.clone(), task).await;
+            assert!(res.is_ok(),
+                    "Launch should success {}", task_id);
+
+            let expected = if i % 3 == 0 { TaskState::Failed } else { TaskState::Success };
+            assert_eq!(manager.wait_for_finish(&task_id).await, expected,
+                       "Unexpected finished state {}", task_id);
+            assert_eq!(waiter.await.unwrap(), expected,
+                       "Unexpected finished state of early waiter {}", task_id);
+            if expected == TaskState::Failed {
+                return;
+            }
+
+            // revoke
+            let revoke_task = async move {
+                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+                Ok::<(), ()>(())
+            };
+            let res = manager.revoke_task(&task_id, revoke_task).await;
+            assert!(res.is_ok());
+            assert_eq!(manager.wait_for_state(&task_id, &[TaskState::NotFound]).await, TaskState::NotFound,
+                       "Should be removed after revoking {}", task_id);
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        if let Err(e) = res {
+            if e.is_panic() {
+                std::panic::resume_unwind(e.into_panic());
+            }
+        }
+    }
+}